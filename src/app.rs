@@ -0,0 +1,2044 @@
+//! Application state and key handling, kept apart from the terminal so the
+//! main loop only has to draw and forward events.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, Receiver, TryRecvError},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Local};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use reqwest::{blocking::Client, cookie::CookieStore, Url};
+
+use lazycurl::{
+    baseline::{self, Baselines},
+    batch::{self, Batch},
+    binary,
+    compare::Comparison,
+    config::{self, Config},
+    dotenv, form,
+    graphql::{self, Entry},
+    history::{self, History},
+    http, httpfile,
+    jars::Jars,
+    oauth::TokenSource,
+    preferences::Preferences,
+    query,
+    recording::Recordings,
+    request::{self, Request, METHODS},
+    response::{self, Connection, Response},
+    save,
+    stats::Stats,
+    stream::{self, Chunk},
+    template, trace, xml,
+};
+
+use crate::{
+    lines::IndexedText,
+    nav::{self, KeyRepeat},
+    rows::RowEditor,
+    search::Search,
+};
+
+/// Smallest and largest timeout the settings slider allows, in seconds.
+pub const TIMEOUT_RANGE: (u64, u64) = (1, 300);
+
+/// How often a streaming body is redrawn while it arrives, unless this
+/// many bytes come in first.
+const STREAM_REFRESH: Duration = Duration::from_millis(100);
+const STREAM_REFRESH_BYTES: usize = 64 * 1024;
+
+/// What the response pane shows before anything is sent.
+const RESPONSE_PLACEHOLDER: &str = "Response will appear here...";
+
+/// What to do with the text typed into the prompt once it is confirmed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PromptAction {
+    ExportHttp,
+    ImportHttp,
+    SaveResponse,
+    ExportMetadata,
+    Batch,
+    BatchConcurrency,
+    CookieOverride,
+    CookieJar,
+    LoadDotenv,
+    Search,
+    HostOverride,
+    SniOverride,
+    PromptValue, // For a `{{prompt:Label}}` placeholder of the request being sent
+}
+
+/// A one-line input shown in the status bar, e.g. for file names.
+#[derive(Debug, Clone)]
+pub struct Prompt {
+    pub label: String,
+    pub input: String,
+    pub action: PromptAction,
+}
+
+/// The area that receives keys not handled globally.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Focus {
+    Url,
+    Headers,
+    Params,
+    Body,
+    Response,
+}
+
+/// The last request sent and how it went, for the status bar badge.
+pub struct LastSent {
+    pub method: &'static str,
+    pub status: Option<u16>, // `None` if sending failed
+    pub elapsed: Option<Duration>,
+}
+
+/// Where polling is between sends. The next send is scheduled only once
+/// the previous response is in, so a slow endpoint never gets overlapping
+/// requests.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Poll {
+    WaitingForResponse,
+    WaitingForInterval(Instant), // When the next send is due
+}
+
+/// How the connection a response came over compares with earlier ones.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Reuse {
+    Reused,
+    New,
+    /// New although the last connection to the same server was left open,
+    /// as far as the server said.
+    NotReused,
+}
+
+/// The latest connection to a server, for telling reuse from reconnecting.
+struct Seen {
+    connection: Connection,
+    bare: bool, // Made by the client without the cookie jar, which pools apart
+    keep_alive: bool,
+}
+
+/// What the external editor is opened on.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EditTarget {
+    Body,
+    Expected, // The request's expected response
+}
+
+/// A send waiting for the values of its `{{prompt:Label}}` placeholders,
+/// which are asked for one at a time.
+struct PendingSend {
+    save: bool,
+    verbose: bool,
+    labels: Vec<String>,            // Still to ask, in order
+    answers: Vec<(String, String)>, // Label and value typed, never stored
+}
+
+/// A request that has been sent and not finished yet.
+struct InFlight {
+    request: Request, // As it was when sent, for recording
+    events: Receiver<http::Event>,
+    save: bool,    // Write the response to a file once it arrives
+    verbose: bool, // Show the capture of what went over the connection
+    sent_at: Instant,
+    received: usize, // Body bytes so far
+    /// When the response pane last showed the body, and how much of it.
+    shown: (Instant, usize),
+}
+
+pub struct App {
+    pub config: Config,
+    pub config_files: Vec<PathBuf>, // Where `config` was merged from
+    pub preferences: Preferences,
+    pub request: Request,
+    pub response: Option<Response>,
+    pub response_text: IndexedText,
+    pub response_scroll: usize, // First response line shown
+    /// `+`/`-`/` ` per line while `response_text` is a diff from the
+    /// expected response or baseline, empty otherwise.
+    pub diff_marks: Vec<char>,
+    /// Lines added and removed compared to the request's expected response,
+    /// or else its pinned baseline, if it has one. `(0, 0)` means the
+    /// response matches it.
+    pub baseline_changes: Option<(usize, usize)>,
+    pub diffed_expected: bool, // `baseline_changes` are from the expected response
+    pub show_baseline_diff: bool,
+    /// JSON nesting levels shown expanded, `None` for all. Kept across
+    /// sends and view changes.
+    pub fold_depth: Option<usize>,
+    pub sort_keys: bool,                // Show JSON object keys sorted
+    pub show_base64: bool,              // Show the response body decoded from base64
+    pub decoded_base64: Option<usize>,  // Bytes decoded from the body shown
+    pub jsonp_callback: Option<String>, // Of the JSONP body shown unwrapped
+    pub trace: Option<String>,          // What went over the connection, for a verbose send
+    pub search: Search,                 // Of the response pane, off while the pattern is empty
+    /// Rows of a CSV/TSV response, shown as a table instead of the text.
+    pub response_table: Option<Vec<Vec<String>>>,
+    pub highlight_line: Option<usize>, // Response line marked by a jump
+    pub focus: Focus,
+    pub options_mode: usize, // 0: Headers, 1: Body, 2: Params, 3: Cookies, 4: Wire headers
+    pub headers_editor: RowEditor, // Headers as edited, one `Name: value` per row
+    pub params_editor: RowEditor, // Params as edited, one `name=value` per row
+    pub decoded_query: Option<String>, // The URL as typed, while its query shows decoded
+    pub prompt: Option<Prompt>,
+    pub batch: Option<Batch>,
+    batch_urls: Vec<String>, // Read for the batch whose concurrency is asked for
+    /// A/B comparison shown instead of the response while set.
+    pub comparison: Option<Comparison>, // Shown in place of the response while present
+    pub history: History,
+    pub last_sent: Option<LastSent>,
+    pub history_selected: Option<usize>, // Selected entry while the history view is open
+    pub snippet_selected: Option<usize>, // Selected entry while the snippet menu is open
+    pub timeout_setting: Option<u64>,    // Timeout being picked while the settings are open
+    /// What the GraphQL endpoint offers, from the last introspection.
+    pub schema: Vec<Entry>,
+    pub schema_selected: Option<usize>, // Selected entry while the schema browser is open
+    /// The request as last loaded or saved, `None` if it's a new one.
+    pub saved: Option<Request>,
+    pub status: Option<String>, // Feedback from the last action, shown in the status bar
+    pub should_quit: bool,
+    /// Set when the body or expected response should be edited in
+    /// `$EDITOR`; the main loop suspends the UI and hands the result to
+    /// [`App::finish_edit`].
+    pub edit: Option<EditTarget>,
+    /// Set when the response text should be shown in `$PAGER`, which the
+    /// main loop suspends the UI for.
+    pub page_response: bool,
+    pub show_timestamps: bool, // Prefix streamed lines with their arrival time
+    pub recording: bool,       // Store every response received
+    pub replaying: bool,       // Serve recorded responses instead of sending
+    pub polling: Option<Poll>, // `None` while polling is off
+    pub stats: Stats,          // Requests sent this session
+    pub reuse: Option<Reuse>,  // Of the response shown, `None` when unknown
+    connections: Vec<Seen>,
+    pub show_stats: bool,
+    recordings: Recordings,
+    baselines: Baselines,
+    response_request: Option<Request>, // What the response answered, as typed
+    response_at: Option<DateTime<Local>>, // When it was received
+    in_flight: Option<InFlight>,
+    introspection: Option<Receiver<Result<Vec<Entry>, String>>>,
+    chunks: Vec<Chunk>, // Body of the current response as it arrived
+    key_repeat: KeyRepeat,
+    pending_send: Option<PendingSend>,
+    ctrl_x: bool,               // First half of a Ctrl+X Ctrl+E/R chord was pressed
+    body_cursor: Option<usize>, // Byte offset typing goes to, `None` for the end
+    pub jars: Arc<Jars>,
+    auth: Option<Arc<TokenSource>>, // From the `[oauth2]` config section
+    client: Client,                 // Uses the cookie jar
+    bare_client: Client,            // For requests with a cookie override
+}
+
+impl App {
+    pub fn new(
+        config: Config,
+        recordings: Recordings,
+        history: History,
+        baselines: Baselines,
+    ) -> Result<Self, String> {
+        let jars = Arc::new(Jars::default());
+        let (client, bare_client) = clients(&config, &jars)?;
+        let auth = config.oauth2.clone().map(|o| Arc::new(TokenSource::new(o)));
+        Ok(Self {
+            config,
+            config_files: Vec::new(),
+            preferences: Preferences::default(),
+            request: Request::default(),
+            response: None,
+            response_text: RESPONSE_PLACEHOLDER.into(),
+            response_scroll: 0,
+            diff_marks: Vec::new(),
+            baseline_changes: None,
+            diffed_expected: false,
+            show_baseline_diff: true,
+            fold_depth: None,
+            sort_keys: false,
+            show_base64: false,
+            decoded_base64: None,
+            jsonp_callback: None,
+            trace: None,
+            search: Search::default(),
+            response_table: None,
+            highlight_line: None,
+            focus: Focus::Url,
+            options_mode: 0,
+            headers_editor: RowEditor::default(),
+            params_editor: RowEditor::default(),
+            decoded_query: None,
+            prompt: None,
+            batch: None,
+            batch_urls: Vec::new(),
+            comparison: None,
+            history,
+            last_sent: None,
+            history_selected: None,
+            snippet_selected: None,
+            timeout_setting: None,
+            schema: Vec::new(),
+            schema_selected: None,
+            saved: None,
+            status: None,
+            should_quit: false,
+            edit: None,
+            page_response: false,
+            show_timestamps: false,
+            recording: false,
+            replaying: false,
+            polling: None,
+            stats: Stats::default(),
+            reuse: None,
+            connections: Vec::new(),
+            show_stats: false,
+            recordings,
+            baselines,
+            response_request: None,
+            response_at: None,
+            in_flight: None,
+            introspection: None,
+            chunks: Vec::new(),
+            key_repeat: KeyRepeat::default(),
+            pending_send: None,
+            ctrl_x: false,
+            body_cursor: None,
+            jars,
+            auth,
+            client,
+            bare_client,
+        })
+    }
+
+    pub fn on_key(&mut self, key: KeyEvent) {
+        if self.prompt.is_some() {
+            self.on_prompt_key(key);
+            return;
+        }
+        // A status message is shown until the next key press
+        self.status = None;
+        if self.history_selected.is_some() {
+            self.on_history_key(key);
+            return;
+        }
+        if self.snippet_selected.is_some() {
+            self.on_snippet_key(key);
+            return;
+        }
+        if self.timeout_setting.is_some() {
+            self.on_settings_key(key);
+            return;
+        }
+        if self.schema_selected.is_some() {
+            self.on_schema_key(key);
+            return;
+        }
+        if self.show_stats {
+            self.on_stats_key(key);
+            return;
+        }
+
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        if std::mem::take(&mut self.ctrl_x) && ctrl {
+            let target = match key.code {
+                KeyCode::Char('e') => Some(EditTarget::Body),
+                KeyCode::Char('r') => Some(EditTarget::Expected),
+                _ => None,
+            };
+            if target.is_some() {
+                self.edit = target;
+                return;
+            }
+        }
+        let shortcut = self.config.clear_response_key;
+        if key.code == KeyCode::Char(shortcut.key)
+            && key.modifiers.contains(KeyModifiers::CONTROL) == shortcut.ctrl
+            && key.modifiers.contains(KeyModifiers::ALT) == shortcut.alt
+        {
+            self.clear_response();
+            return;
+        }
+        match key.code {
+            KeyCode::Esc if self.comparison.is_some() => self.comparison = None,
+            KeyCode::Esc => match &mut self.batch {
+                Some(batch) if !batch.finished && !batch.cancelled => {
+                    batch.cancel();
+                    self.status = Some("Batch cancelled".into());
+                }
+                Some(_) => self.batch = None,
+                None => self.should_quit = true,
+            },
+            // Inside the body, Tab fills in the fields a snippet left behind
+            KeyCode::Tab if self.focus == Focus::Body && self.next_snippet_field() => {}
+            KeyCode::Tab => {
+                self.focus = match self.focus {
+                    Focus::Url => Focus::Headers,
+                    Focus::Headers => Focus::Params,
+                    Focus::Params => Focus::Body,
+                    Focus::Body => Focus::Response,
+                    Focus::Response => Focus::Url,
+                };
+                self.focus_changed();
+            }
+            KeyCode::BackTab => {
+                self.focus = match self.focus {
+                    Focus::Url => Focus::Response,
+                    Focus::Headers => Focus::Url,
+                    Focus::Params => Focus::Headers,
+                    Focus::Body => Focus::Params,
+                    Focus::Response => Focus::Body,
+                };
+                self.focus_changed();
+            }
+            // Terminals without enhanced key reporting send Ctrl+Enter as Ctrl+J
+            KeyCode::Enter | KeyCode::Char('j') if ctrl => self.request_send(false, false),
+            KeyCode::Char('d') if ctrl => {
+                // Swap the field between its decoded and wire forms
+                self.request.url = match self.decoded_query.take() {
+                    // Left alone, the URL goes back to exactly what was typed
+                    Some(typed) if query::decode(&typed) == self.request.url => typed,
+                    Some(_) => query::encode(&self.request.url),
+                    None => {
+                        self.decoded_query = Some(self.request.url.clone());
+                        query::decode(&self.request.url)
+                    }
+                };
+                self.request.sync_params();
+            }
+            KeyCode::Char('t') if ctrl => {
+                self.request.url = toggle_scheme(&self.request.url);
+            }
+            KeyCode::Char('e') if ctrl => {
+                self.open_prompt("Export .http to", "request.http", PromptAction::ExportHttp)
+            }
+            KeyCode::Char('o') if ctrl => self.open_prompt(
+                "Import .http from",
+                "request.http",
+                PromptAction::ImportHttp,
+            ),
+            KeyCode::Char('r') if ctrl => {
+                self.recording = !self.recording;
+                self.status = Some(format!(
+                    "Recording {}",
+                    if self.recording { "on" } else { "off" }
+                ));
+            }
+            KeyCode::Char('p') if ctrl => {
+                self.replaying = !self.replaying;
+                self.status = Some(format!(
+                    "Replay {}",
+                    if self.replaying {
+                        "on: recorded responses are served offline"
+                    } else {
+                        "off"
+                    }
+                ));
+            }
+            KeyCode::Char('g') if ctrl => {
+                self.show_timestamps = !self.show_timestamps;
+                self.refresh_response_text();
+            }
+            KeyCode::F(2) if self.history.entries.is_empty() => {
+                self.status = Some("History is empty".into());
+            }
+            KeyCode::F(2) => self.history_selected = Some(0),
+            KeyCode::F(3) => self.pin_baseline(),
+            KeyCode::F(4) => self.compare(),
+            KeyCode::F(5) => self.reload_config(),
+            KeyCode::F(6) => {
+                // The config file may hold a timeout the slider can't show
+                let timeout = self.config.timeout_secs;
+                self.timeout_setting = Some(timeout.clamp(TIMEOUT_RANGE.0, TIMEOUT_RANGE.1));
+            }
+            KeyCode::F(7) => self.introspect(),
+            KeyCode::F(8) => self.toggle_polling(),
+            KeyCode::F(9) => self.show_stats = true,
+            KeyCode::Char('f') if ctrl => self.toggle_body_format(),
+            KeyCode::Char('l') if ctrl => self.format_body(false),
+            KeyCode::Char('w') if ctrl => self.format_body(true),
+            KeyCode::Char('x') if ctrl => {
+                self.ctrl_x = true;
+                self.status = Some(
+                    "Ctrl+X- (Ctrl+E: edit body, Ctrl+R: expected response in $EDITOR)".into(),
+                );
+            }
+            KeyCode::Char('k') if ctrl => {
+                let current = self.request.cookies.clone();
+                self.open_prompt(
+                    "Cookie override (empty: use the jar)",
+                    &current,
+                    PromptAction::CookieOverride,
+                );
+                self.options_mode = 3;
+            }
+            KeyCode::Char('k') if key.modifiers.contains(KeyModifiers::ALT) => {
+                let active = self.jars.active();
+                self.open_prompt(
+                    "Cookie jar (a new name creates it)",
+                    &active,
+                    PromptAction::CookieJar,
+                );
+                self.options_mode = 3;
+            }
+            KeyCode::Char('v') if key.modifiers.contains(KeyModifiers::ALT) => {
+                self.request_send(false, true)
+            }
+            KeyCode::Char('e') if key.modifiers.contains(KeyModifiers::ALT) => {
+                self.open_prompt("Load variables from", ".env", PromptAction::LoadDotenv)
+            }
+            KeyCode::Char('b') if key.modifiers.contains(KeyModifiers::ALT) => {
+                self.toggle_body_base64()
+            }
+            KeyCode::Char('u') if ctrl => {
+                let current = self.request.host.clone();
+                self.open_prompt(
+                    "Host header override (empty: from the URL)",
+                    &current,
+                    PromptAction::HostOverride,
+                );
+                self.options_mode = 4;
+            }
+            KeyCode::Char('b') if ctrl => {
+                self.open_prompt("Batch URLs file", "urls.txt", PromptAction::Batch)
+            }
+            KeyCode::Char('s') if ctrl => match &self.response {
+                Some(response) => {
+                    let name = save::default_file_name(response);
+                    self.open_prompt("Save response to", &name, PromptAction::SaveResponse);
+                }
+                None => self.status = Some("No response to save".into()),
+            },
+            _ => match self.focus {
+                Focus::Url => self.on_url_key(key),
+                Focus::Headers => self.on_headers_key(key),
+                Focus::Params => self.on_params_key(key),
+                Focus::Body => self.on_body_key(key),
+                Focus::Response => self.on_response_key(key),
+            },
+        }
+    }
+
+    /// Keys while the history view is open.
+    fn on_history_key(&mut self, key: KeyEvent) {
+        let Some(selected) = self.history_selected else {
+            return;
+        };
+        match key.code {
+            KeyCode::Esc | KeyCode::F(2) => self.history_selected = None,
+            KeyCode::Char('t') => {
+                self.preferences.relative_history_times = !self.preferences.relative_history_times;
+                if let Err(err) = self.preferences.save() {
+                    self.status = Some(format!("Preference not saved: {}", err));
+                }
+            }
+            KeyCode::Up
+            | KeyCode::Down
+            | KeyCode::PageUp
+            | KeyCode::PageDown
+            | KeyCode::Home
+            | KeyCode::End => {
+                let delta = self.list_delta(key.code);
+                let len = self.history.entries.len();
+                self.history_selected = Some(nav::move_index(selected, delta, len));
+            }
+            KeyCode::Enter => {
+                // Edit a copy so the history entry itself stays as it was
+                if let Some(entry) = self.history.entries.get(selected) {
+                    self.request = entry.request.clone();
+                    self.request.sync_params();
+                    self.saved = Some(self.request.clone());
+                    self.decoded_query = None;
+                    self.focus = Focus::Url;
+                    self.status = Some(format!(
+                        "Loaded request sent at {} for editing",
+                        entry.sent_at.format("%Y-%m-%d %H:%M:%S")
+                    ));
+                }
+                self.history_selected = None;
+            }
+            _ => {}
+        }
+    }
+
+    /// Keys while the snippet menu is open.
+    fn on_snippet_key(&mut self, key: KeyEvent) {
+        let Some(selected) = self.snippet_selected else {
+            return;
+        };
+        match key.code {
+            KeyCode::Esc => self.snippet_selected = None,
+            KeyCode::Up
+            | KeyCode::Down
+            | KeyCode::PageUp
+            | KeyCode::PageDown
+            | KeyCode::Home
+            | KeyCode::End => {
+                let delta = self.list_delta(key.code);
+                let len = self.config.snippets.len();
+                self.snippet_selected = Some(nav::move_index(selected, delta, len));
+            }
+            KeyCode::Enter => {
+                if let Some(snippet) = self.config.snippets.get(selected) {
+                    let text = snippet.text.clone();
+                    self.insert_body_text(&text);
+                    self.next_snippet_field();
+                }
+                self.snippet_selected = None;
+            }
+            _ => {}
+        }
+    }
+
+    /// Keys while the session stats are shown.
+    fn on_stats_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Esc | KeyCode::F(9) => self.show_stats = false,
+            KeyCode::Char('r') => {
+                self.stats = Stats::default();
+                self.status = Some("Session stats reset".into());
+            }
+            _ => {}
+        }
+    }
+
+    /// Starts re-sending the request every `poll_interval_secs`, or stops.
+    fn toggle_polling(&mut self) {
+        if self.polling.take().is_some() {
+            self.status = Some("Polling stopped".into());
+        } else if self.request.url.is_empty() {
+            self.status = Some("Type a URL to poll first".into());
+        } else if !template::prompt_labels(&self.request).is_empty() {
+            self.status = Some("Can't poll a request with {{prompt:...}} placeholders".into());
+        } else {
+            self.polling = Some(Poll::WaitingForInterval(Instant::now()));
+            self.status = Some(format!(
+                "Polling every {} s after each response (F8: stop)",
+                self.config.poll_interval_secs
+            ));
+        }
+    }
+
+    /// Sends the GraphQL introspection query to the current URL in the
+    /// background; the schema browser opens once it is answered.
+    fn introspect(&mut self) {
+        if self.introspection.is_some() {
+            self.status = Some("Already waiting for the GraphQL schema".into());
+            return;
+        }
+        if self.request.url.is_empty() {
+            self.status = Some("Type the GraphQL endpoint URL first".into());
+            return;
+        }
+        let client = if self.request.cookies.is_empty() {
+            self.client.clone()
+        } else {
+            self.bare_client.clone()
+        };
+        let (request, auth) = (self.request.clone(), self.auth.clone());
+        let config = self.config.clone();
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = tx.send(graphql::introspect(
+                &client,
+                &config,
+                &request,
+                auth.as_deref(),
+            ));
+        });
+        self.introspection = Some(rx);
+        self.status = Some("Fetching the GraphQL schema...".into());
+    }
+
+    /// Keys while the schema browser is open.
+    fn on_schema_key(&mut self, key: KeyEvent) {
+        let Some(selected) = self.schema_selected else {
+            return;
+        };
+        match key.code {
+            KeyCode::Esc | KeyCode::F(7) => self.schema_selected = None,
+            KeyCode::Up
+            | KeyCode::Down
+            | KeyCode::PageUp
+            | KeyCode::PageDown
+            | KeyCode::Home
+            | KeyCode::End => {
+                let delta = self.list_delta(key.code);
+                let len = self.schema.len();
+                self.schema_selected = Some(nav::move_index(selected, delta, len));
+            }
+            KeyCode::Enter => {
+                let Some(entry) = self.schema.get(selected) else {
+                    return;
+                };
+                match graphql::scaffold(entry) {
+                    Some(body) => {
+                        self.status = Some(format!("Request for {} scaffolded", entry.name));
+                        self.request.method = request::method_index("POST").unwrap_or_default();
+                        self.request.set_header("Content-Type", "application/json");
+                        self.request.body = body;
+                        self.body_cursor = None;
+                        self.schema_selected = None;
+                        self.focus = Focus::Body;
+                        self.focus_changed();
+                    }
+                    None => {
+                        self.status = Some("Types can't be sent, pick a query or mutation".into())
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Keys while the settings are open: Left/Right move the timeout
+    /// slider, Enter applies it to the next request.
+    fn on_settings_key(&mut self, key: KeyEvent) {
+        let Some(timeout) = self.timeout_setting else {
+            return;
+        };
+        let picked = match key.code {
+            KeyCode::Left => timeout.saturating_sub(1),
+            KeyCode::Right => timeout + 1,
+            KeyCode::PageDown => timeout.saturating_sub(10),
+            KeyCode::PageUp => timeout + 10,
+            KeyCode::Home => TIMEOUT_RANGE.0,
+            KeyCode::End => TIMEOUT_RANGE.1,
+            KeyCode::Esc | KeyCode::F(6) => {
+                self.timeout_setting = None;
+                return;
+            }
+            KeyCode::Enter => {
+                self.timeout_setting = None;
+                self.set_timeout(timeout);
+                return;
+            }
+            _ => timeout,
+        };
+        self.timeout_setting = Some(picked.clamp(TIMEOUT_RANGE.0, TIMEOUT_RANGE.1));
+    }
+
+    /// Uses `secs` as the timeout from the next request on, until the
+    /// config is reloaded.
+    fn set_timeout(&mut self, secs: u64) {
+        let config = Config {
+            timeout_secs: secs,
+            ..self.config.clone()
+        };
+        match clients(&config, &self.jars) {
+            Ok((client, bare_client)) => {
+                self.config = config;
+                self.client = client;
+                self.bare_client = bare_client;
+                self.status = Some(format!(
+                    "Timeout set to {} s for this session (config.toml unchanged)",
+                    secs
+                ));
+            }
+            Err(err) => self.status = Some(format!("Timeout not changed: {}", err)),
+        }
+    }
+
+    /// Keys while the URL field has focus: typing edits the URL and the
+    /// arrows pick the method.
+    fn on_url_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Up
+            | KeyCode::Down
+            | KeyCode::PageUp
+            | KeyCode::PageDown
+            | KeyCode::Home
+            | KeyCode::End => {
+                let delta = self.list_delta(key.code);
+                self.request.method = nav::move_index(self.request.method, delta, METHODS.len());
+            }
+            KeyCode::Char('H') => self.options_mode = 0,
+            KeyCode::Char('B') => self.options_mode = 1,
+            KeyCode::Char('P') => self.options_mode = 2,
+            KeyCode::Char('C') => self.options_mode = 3,
+            KeyCode::Char('W') => self.options_mode = 4,
+            KeyCode::Enter => self.request_send(key.modifiers.contains(KeyModifiers::ALT), false),
+            KeyCode::Char(c) => {
+                self.request.url.push(c);
+                self.request.sync_params();
+            }
+            KeyCode::Backspace => {
+                self.request.url.pop();
+                self.request.sync_params();
+            }
+            _ => {}
+        }
+    }
+
+    /// Keys shared by the headers and params editors. Returns whether the
+    /// rows changed.
+    fn on_rows_key(editor: &mut RowEditor, key: KeyEvent, delta: isize) -> bool {
+        match key.code {
+            KeyCode::Up
+            | KeyCode::Down
+            | KeyCode::PageUp
+            | KeyCode::PageDown
+            | KeyCode::Home
+            | KeyCode::End => {
+                let len = editor.rows.len();
+                editor.select(nav::move_index(editor.selected, delta, len));
+                false
+            }
+            KeyCode::Char('y') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                editor.duplicate();
+                true
+            }
+            KeyCode::Enter => {
+                editor.new_row();
+                true
+            }
+            KeyCode::Char(c) => {
+                editor.push(c);
+                true
+            }
+            KeyCode::Backspace => {
+                editor.pop();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Keys while the headers editor has focus. Rows without a `:` yet are
+    /// left out of the request until they get one.
+    fn on_headers_key(&mut self, key: KeyEvent) {
+        let delta = self.list_delta(key.code);
+        if !Self::on_rows_key(&mut self.headers_editor, key, delta) {
+            return;
+        }
+        self.request.headers = self
+            .headers_editor
+            .rows
+            .iter()
+            .filter_map(|row| row.split_once(':'))
+            .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+            .filter(|(name, _)| !name.is_empty())
+            .collect();
+    }
+
+    /// Keys while the params editor has focus. Every edit is written back
+    /// to the URL's query string.
+    fn on_params_key(&mut self, key: KeyEvent) {
+        let delta = self.list_delta(key.code);
+        if !Self::on_rows_key(&mut self.params_editor, key, delta) {
+            return;
+        }
+        self.request.params = self
+            .params_editor
+            .rows
+            .iter()
+            .filter(|row| !row.is_empty())
+            .flat_map(|row| query::parse_params(row))
+            .collect();
+        self.request.apply_params();
+    }
+
+    /// Shows the options matching the newly focused area.
+    fn focus_changed(&mut self) {
+        match self.focus {
+            Focus::Headers => {
+                self.options_mode = 0;
+                let rows = self
+                    .request
+                    .headers
+                    .iter()
+                    .map(|(name, value)| format!("{}: {}", name, value))
+                    .collect();
+                self.headers_editor = RowEditor::new(rows);
+            }
+            Focus::Params => {
+                self.options_mode = 2;
+                let rows = self
+                    .request
+                    .params
+                    .iter()
+                    .map(|param| query::join_params(std::slice::from_ref(param)))
+                    .collect();
+                self.params_editor = RowEditor::new(rows);
+            }
+            Focus::Body => self.options_mode = 1,
+            Focus::Url | Focus::Response => {}
+        }
+    }
+
+    /// Keys while the body editor has focus. Enter inserts a newline unless
+    /// the config makes it send.
+    fn on_body_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Enter if self.config.body_enter_sends => {
+                self.request_send(key.modifiers.contains(KeyModifiers::ALT), false)
+            }
+            KeyCode::Enter => self.insert_body_text("\n"),
+            KeyCode::Char('n') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                if self.config.snippets.is_empty() {
+                    self.status = Some("No snippets configured".into());
+                } else {
+                    self.snippet_selected = Some(0);
+                }
+            }
+            KeyCode::Char(c) => self.insert_body_text(c.encode_utf8(&mut [0; 4])),
+            KeyCode::Backspace => {
+                let cursor = self.body_cursor();
+                if let Some((start, _)) = self.request.body[..cursor].char_indices().next_back() {
+                    self.request.body.remove(start);
+                    self.body_cursor = Some(start);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Where typing goes in the body. Edits made elsewhere can leave the
+    /// stored offset out of range, which means the end.
+    pub fn body_cursor(&self) -> usize {
+        let body = &self.request.body;
+        self.body_cursor
+            .filter(|&cursor| cursor <= body.len() && body.is_char_boundary(cursor))
+            .unwrap_or(body.len())
+    }
+
+    fn insert_body_text(&mut self, text: &str) {
+        let cursor = self.body_cursor();
+        self.request.body.insert_str(cursor, text);
+        self.body_cursor = Some(cursor + text.len());
+    }
+
+    /// Removes the first `${...}` snippet field from the body and moves
+    /// the cursor there. Returns whether there was one.
+    fn next_snippet_field(&mut self) -> bool {
+        let body = &self.request.body;
+        let Some(start) = body.find("${") else {
+            return false;
+        };
+        let Some(len) = body[start..].find('}').map(|end| end + 1) else {
+            return false;
+        };
+        self.request.body.replace_range(start..start + len, "");
+        self.body_cursor = Some(start);
+        true
+    }
+
+    /// Keys while the response pane has focus: scrolling and jumping.
+    fn on_response_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Up
+            | KeyCode::Down
+            | KeyCode::PageUp
+            | KeyCode::PageDown
+            | KeyCode::Home
+            | KeyCode::End => {
+                let delta = self.list_delta(key.code);
+                let lines = match self.comparison.as_ref().and_then(|c| c.columns.as_ref()) {
+                    Some([a, _]) => a.len(),
+                    None => self.response_text.line_count(),
+                };
+                self.response_scroll = nav::move_index(self.response_scroll, delta, lines);
+            }
+            KeyCode::Char('e') => self.jump_to_error(),
+            KeyCode::Char('p') => self.page_response = true,
+            KeyCode::Char('m') if self.response.is_some() => self.open_prompt(
+                "Save response metadata to",
+                "response-meta.json",
+                PromptAction::ExportMetadata,
+            ),
+            KeyCode::Char('c') => self.fold(Some(1)),
+            KeyCode::Char('o') => self.fold(None),
+            KeyCode::Char('s') => {
+                self.sort_keys = !self.sort_keys;
+                self.refresh_response_text();
+            }
+            KeyCode::Char('/') => {
+                let label = format!("Search ({})", self.search.mode());
+                let pattern = self.search.pattern.clone();
+                self.open_prompt(label, &pattern, PromptAction::Search);
+            }
+            KeyCode::Char(c @ ('n' | 'N')) if !self.search.pattern.is_empty() => {
+                if let Some(line) = self.search.step(c == 'N') {
+                    self.response_scroll = line;
+                }
+                self.status = Some(self.search_status());
+            }
+            KeyCode::Char('r') => {
+                self.search.regex = !self.search.regex;
+                self.status = Some(self.run_search());
+            }
+            KeyCode::Char('i') => {
+                self.search.case_sensitive = !self.search.case_sensitive;
+                self.status = Some(self.run_search());
+            }
+            KeyCode::Char('b') if self.response.is_some() => {
+                self.show_base64 = !self.show_base64;
+                self.refresh_response_text();
+            }
+            KeyCode::Char(c @ '1'..='9') => self.fold(c.to_digit(10).map(|d| d as usize)),
+            KeyCode::Char('v') if self.baseline_changes.is_some() => {
+                self.show_baseline_diff = !self.show_baseline_diff;
+                self.refresh_response_text();
+            }
+            KeyCode::Enter => self.request_send(key.modifiers.contains(KeyModifiers::ALT), false),
+            _ => {}
+        }
+    }
+
+    /// Scrolls to and highlights the first line holding one of the
+    /// configured error keys.
+    fn jump_to_error(&mut self) {
+        let keys: Vec<String> = self
+            .config
+            .error_keys
+            .iter()
+            .map(|key| format!("{}:", serde_json::Value::from(key.as_str())))
+            .collect();
+        let found = self.response_text.as_str().lines().position(|line| {
+            let line = line.trim_start();
+            keys.iter().any(|key| line.starts_with(key.as_str()))
+        });
+
+        match found {
+            Some(line) => {
+                self.highlight_line = Some(line);
+                self.response_scroll = line;
+            }
+            None => {
+                self.highlight_line = None;
+                self.status = Some(format!(
+                    "No error field ({}) in the response",
+                    self.config.error_keys.join(", ")
+                ));
+            }
+        }
+    }
+
+    /// Re-reads the config files and applies them, reporting which settings
+    /// changed. Any invalid file leaves the current config in place.
+    fn reload_config(&mut self) {
+        let layered = config::load();
+        if !layered.warnings.is_empty() {
+            self.status = Some(format!(
+                "Config reload failed, keeping current: {}",
+                layered.warnings.join("; ")
+            ));
+            return;
+        }
+        let new = layered.config;
+        let (client, bare_client) = match clients(&new, &self.jars) {
+            Ok(clients) => clients,
+            Err(err) => {
+                self.status = Some(format!("Config reload failed, keeping current: {}", err));
+                return;
+            }
+        };
+
+        let changes = self.config.changes(&new);
+        // Keep the cached token unless the way of getting one changed
+        if new.oauth2 != self.config.oauth2 {
+            self.auth = new.oauth2.clone().map(|o| Arc::new(TokenSource::new(o)));
+        }
+        self.config = new;
+        self.config_files = layered.loaded;
+        self.client = client;
+        self.bare_client = bare_client;
+        self.status = Some(if changes.is_empty() {
+            "Config reloaded: no changes".to_string()
+        } else {
+            format!("Config reloaded, changed: {}", changes.join(", "))
+        });
+    }
+
+    /// Rows a navigation key moves a list selection by, taking held-key
+    /// acceleration and the configured page size into account.
+    fn list_delta(&mut self, code: KeyCode) -> isize {
+        let config = &self.config.navigation;
+        let page = config.page_size.max(1) as isize;
+        match code {
+            KeyCode::Up => -(self.key_repeat.step(code, Instant::now(), config) as isize),
+            KeyCode::Down => self.key_repeat.step(code, Instant::now(), config) as isize,
+            KeyCode::PageUp => -page,
+            KeyCode::PageDown => page,
+            KeyCode::Home => isize::MIN,
+            KeyCode::End => isize::MAX,
+            _ => 0,
+        }
+    }
+
+    /// Sends the current request unless one is already in flight or there is
+    /// no URL. With `save` (Alt+Enter) the response is also written to a file.
+    fn request_send(&mut self, save: bool, verbose: bool) {
+        if self.in_flight.is_some() {
+            self.status = Some("A request is already in flight".into());
+        } else if !self.request.url.is_empty() {
+            let labels = template::prompt_labels(&self.request);
+            if labels.is_empty() || self.replaying {
+                self.send(save, verbose, &[]);
+            } else {
+                self.pending_send = Some(PendingSend {
+                    save,
+                    verbose,
+                    labels,
+                    answers: Vec::new(),
+                });
+                self.ask_next_value();
+            }
+        }
+    }
+
+    /// Prompts for the next placeholder value of the pending send, or sends
+    /// once all of them are in.
+    fn ask_next_value(&mut self) {
+        let Some(pending) = self.pending_send.as_mut() else {
+            return;
+        };
+        if pending.labels.is_empty() {
+            if let Some(pending) = self.pending_send.take() {
+                self.send(pending.save, pending.verbose, &pending.answers);
+            }
+        } else {
+            let label = pending.labels[0].clone();
+            self.open_prompt(label, "", PromptAction::PromptValue);
+        }
+    }
+
+    /// Empties the response pane back to its placeholder. The request and
+    /// anything in flight are left alone.
+    fn clear_response(&mut self) {
+        self.chunks.clear();
+        self.response = None;
+        self.response_request = None;
+        self.response_at = None;
+        self.reuse = None;
+        self.trace = None;
+        self.show_base64 = false;
+        self.search.clear();
+        // Without a response this only drops what was derived from it
+        self.render_response_text();
+        self.response_text = RESPONSE_PLACEHOLDER.into();
+        self.response_scroll = 0;
+        self.status = Some("Response cleared".into());
+    }
+
+    /// Sends the current request; with `save`, a successful response is
+    /// written to a file named after its content type, with `verbose` it's
+    /// shown after what went over the connection.
+    /// `answers` fill in the `{{prompt:Label}}` placeholders of the copy
+    /// that is sent.
+    fn send(&mut self, save: bool, verbose: bool, answers: &[(String, String)]) {
+        self.chunks.clear();
+        self.response_scroll = 0;
+        self.response = None;
+        self.trace = None;
+
+        if self.replaying {
+            let result = self
+                .recordings
+                .replay(&self.request)
+                .map(|response| Response {
+                    replayed: true,
+                    ..response.clone()
+                })
+                .ok_or_else(|| "No recorded response for this request (replay is on)".to_string());
+            self.finish(&self.request.clone(), result, save);
+            return;
+        }
+
+        let verbose = verbose
+            && match trace::start() {
+                Ok(()) => true,
+                Err(err) => {
+                    self.status = Some(format!("Sent without verbose capture: {}", err));
+                    false
+                }
+            };
+        self.in_flight = Some(InFlight {
+            request: self.request.clone(),
+            events: self.dispatch(&self.request, answers, verbose),
+            save,
+            verbose,
+            sent_at: Instant::now(),
+            received: 0,
+            shown: (Instant::now(), 0),
+        });
+        self.response_text = "Sending...".into();
+        self.response_table = None;
+    }
+
+    /// Sends `request` with the client it needs, prompts filled in from
+    /// `answers`. A `verbose` send gets a client and connection of its own.
+    fn dispatch(
+        &self,
+        request: &Request,
+        answers: &[(String, String)],
+        verbose: bool,
+    ) -> Receiver<http::Event> {
+        let outgoing = template::fill_prompts(request, answers);
+        match self.client_for(&outgoing, verbose) {
+            Ok(client) => http::spawn(&client, &self.config, &outgoing, self.auth.clone()),
+            Err(err) => {
+                let (tx, rx) = mpsc::channel();
+                let _ = tx.send(http::Event::Done(Err(err)));
+                rx
+            }
+        }
+    }
+
+    /// The client `request` is sent with: the shared one unless it needs
+    /// its own. Requests with a cookie override leave the jar alone.
+    fn client_for(&self, request: &Request, verbose: bool) -> Result<Client, String> {
+        let jar = request.cookies.is_empty().then(|| Arc::clone(&self.jars));
+        match (request.sni.is_empty(), jar) {
+            (_, jar) if verbose => http::verbose_client(&self.config, jar, request),
+            (true, Some(_)) => Ok(self.client.clone()),
+            (true, None) => Ok(self.bare_client.clone()),
+            // The server name is part of the connection, so it gets a client
+            // of its own
+            (false, jar) => http::sni_client(&self.config, jar, request),
+        }
+    }
+
+    /// F4: copies the request as A, then sends A and the edited request (B)
+    /// side by side, pressing again sends both once more.
+    fn compare(&mut self) {
+        let Some(comparison) = &self.comparison else {
+            self.comparison = Some(Comparison::new(self.request.clone()));
+            self.status =
+                Some("Copied the request as A: change it, then F4 sends A and B to compare".into());
+            return;
+        };
+        let events = [
+            self.dispatch(&comparison.a, &[], false),
+            self.dispatch(&self.request, &[], false),
+        ];
+        self.response_scroll = 0;
+        if let Some(comparison) = &mut self.comparison {
+            comparison.sent(self.request.clone(), events);
+        }
+    }
+
+    /// Whether a request has been sent and hasn't finished yet.
+    pub fn is_in_flight(&self) -> bool {
+        self.in_flight.is_some()
+    }
+
+    /// How long ago the request in flight was sent, and how many bytes of
+    /// its body have arrived.
+    pub fn progress(&self) -> Option<(Duration, usize)> {
+        let in_flight = self.in_flight.as_ref()?;
+        Some((in_flight.sent_at.elapsed(), in_flight.received))
+    }
+
+    /// Picks up progress of the request in flight. Called by the main loop
+    /// between key presses.
+    pub fn tick(&mut self) {
+        if let Some(batch) = &mut self.batch {
+            for index in batch.poll() {
+                let answered = batch.results[index]
+                    .as_ref()
+                    .and_then(|outcome| outcome.as_ref().ok());
+                self.stats.record(
+                    &batch.request,
+                    answered.map(|(status, ..)| *status),
+                    answered.map_or(0, |(.., received)| *received),
+                );
+            }
+        }
+        if let Some(Poll::WaitingForInterval(due)) = self.polling {
+            // A send of the user's own counts as this one
+            if self.in_flight.is_some() {
+                self.polling = Some(Poll::WaitingForResponse);
+            } else if Instant::now() >= due && self.prompt.is_none() {
+                // Before sending, as a replayed response finishes at once
+                self.polling = Some(Poll::WaitingForResponse);
+                self.send(false, false, &[]);
+            }
+        }
+        if let Some(comparison) = &mut self.comparison {
+            for side in comparison.poll() {
+                let request = if side == 0 {
+                    Some(&comparison.a)
+                } else {
+                    comparison.b.as_ref()
+                };
+                let response = comparison.results[side]
+                    .as_ref()
+                    .and_then(|result| result.as_ref().ok());
+                if let Some(request) = request {
+                    self.stats.record(
+                        request,
+                        response.map(|r| r.status),
+                        response.map_or(0, |r| r.bytes().len()),
+                    );
+                }
+            }
+        }
+        if let Some(rx) = &self.introspection {
+            let result = match rx.try_recv() {
+                Ok(result) => Some(result),
+                Err(TryRecvError::Empty) => None,
+                Err(TryRecvError::Disconnected) => {
+                    Some(Err("Introspection thread stopped unexpectedly".into()))
+                }
+            };
+            if let Some(result) = result {
+                self.introspection = None;
+                match result {
+                    Ok(entries) if entries.is_empty() => {
+                        self.status = Some("The schema lists no queries, mutations or types".into())
+                    }
+                    Ok(entries) => {
+                        self.schema = entries;
+                        self.schema_selected = Some(0);
+                    }
+                    Err(err) => self.status = Some(err),
+                }
+            }
+        }
+
+        let Some(in_flight) = self.in_flight.as_mut() else {
+            return;
+        };
+
+        let mut done = None;
+        let mut restarted = false;
+        loop {
+            match in_flight.events.try_recv() {
+                Ok(http::Event::Restart) => {
+                    self.chunks.clear();
+                    in_flight.received = 0;
+                    restarted = true;
+                }
+                Ok(http::Event::Chunk(chunk)) => {
+                    in_flight.received += chunk.text.len();
+                    self.chunks.push(chunk);
+                }
+                Ok(http::Event::Done(result)) => {
+                    done = Some(result);
+                    break;
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    done = Some(Err("Request thread stopped unexpectedly".into()));
+                    break;
+                }
+            }
+        }
+
+        if let Some(result) = done {
+            if let Some(in_flight) = self.in_flight.take() {
+                if in_flight.verbose {
+                    self.trace = Some(verbose_trace(&result));
+                }
+                self.finish(&in_flight.request, result, in_flight.save);
+            }
+        } else {
+            // A body trickling in is shown every so often rather than on
+            // every read
+            let (shown_at, shown) = in_flight.shown;
+            let unshown = in_flight.received.saturating_sub(shown);
+            if restarted
+                || unshown >= STREAM_REFRESH_BYTES
+                || (unshown > 0 && shown_at.elapsed() >= STREAM_REFRESH)
+            {
+                in_flight.shown = (Instant::now(), in_flight.received);
+                self.refresh_response_text();
+            }
+        }
+    }
+
+    fn finish(&mut self, request: &Request, result: Result<Response, String>, save: bool) {
+        if self.polling.is_some() {
+            let interval = Duration::from_secs(self.config.poll_interval_secs);
+            self.polling = Some(Poll::WaitingForInterval(Instant::now() + interval));
+        } else if self.config.focus_response {
+            self.focus = Focus::Response;
+        }
+        let replayed = result.as_ref().is_ok_and(|response| response.replayed);
+        self.last_sent = Some(LastSent {
+            method: request.method_name(),
+            status: result.as_ref().ok().map(|response| response.status),
+            elapsed: result.as_ref().ok().map(|response| response.elapsed),
+        });
+        if !replayed {
+            let entry = history::Entry {
+                request: request.clone(),
+                sent_at: Local::now(),
+                status: result.as_ref().ok().map(|response| response.status),
+            };
+            if let Err(err) = self.history.push(entry) {
+                self.status = Some(format!("History not saved: {}", err));
+            }
+            let response = result.as_ref().ok();
+            self.stats.record(
+                request,
+                response.map(|r| r.status),
+                response.map_or(0, |r| r.bytes().len()),
+            );
+        }
+
+        match result {
+            Ok(response) => {
+                if self.recording && !response.replayed {
+                    self.status = Some(match self.recordings.record(request, &response) {
+                        Ok(()) => "Response recorded".to_string(),
+                        Err(err) => format!("Recording failed: {}", err),
+                    });
+                }
+                if !response.replayed && request.cookies.is_empty() {
+                    if let Err(err) = self.persist_jars() {
+                        self.status = Some(format!("Cookie jars not saved: {}", err));
+                    }
+                }
+                self.reuse = self.note_connection(request, &response);
+                // Downloads go straight to the save prompt, named as offered
+                let attachment = save::attachment_name(&response);
+                let name = save.then(|| save::default_file_name(&response));
+                self.response = Some(response);
+                self.response_request = Some(request.clone());
+                self.response_at = Some(Local::now());
+                if let Some(name) = name {
+                    self.status = Some(self.save_response(Path::new(&name)));
+                }
+                self.refresh_response_text();
+                if let Some(name) = attachment.filter(|_| !save && self.prompt.is_none()) {
+                    self.open_prompt("Save attachment to", &name, PromptAction::SaveResponse);
+                }
+            }
+            Err(err) => {
+                self.response_text = match &self.trace {
+                    Some(trace) => format!("{}\n\n{}", trace, err).into(),
+                    None => err.into(),
+                };
+                self.response_table = None;
+                self.response = None;
+                self.reuse = None;
+            }
+        }
+    }
+
+    /// Remembers the connection `response` came over and tells whether it
+    /// was reused. Requests with a TLS SNI override get a client, and so a
+    /// connection, of their own, so they are left out.
+    fn note_connection(&mut self, request: &Request, response: &Response) -> Option<Reuse> {
+        let connection = response.connection?;
+        if !request.sni.is_empty() {
+            return Some(Reuse::New);
+        }
+        let bare = !request.cookies.is_empty();
+        let keep_alive = !response
+            .header("connection")
+            .is_some_and(|value| value.eq_ignore_ascii_case("close"));
+        let previous = self
+            .connections
+            .iter()
+            .position(|seen| seen.bare == bare && seen.connection.remote == connection.remote)
+            .map(|i| self.connections.remove(i));
+        self.connections.push(Seen {
+            connection,
+            bare,
+            keep_alive,
+        });
+        Some(match previous {
+            Some(seen) if seen.connection == connection => Reuse::Reused,
+            Some(seen) if seen.keep_alive => Reuse::NotReused,
+            _ => Reuse::New,
+        })
+    }
+
+    /// Saves the response to `path`, noting the request with it if the
+    /// config asks for that. Returns the status message.
+    fn save_response(&self, path: &Path) -> String {
+        let Some(response) = &self.response else {
+            return "No response to save".into();
+        };
+        let echo = match (&self.response_request, self.response_at) {
+            (Some(request), Some(received_at)) if self.config.echo_request_on_save => {
+                Some(save::Echo {
+                    request,
+                    received_at,
+                })
+            }
+            _ => None,
+        };
+        let saved = match echo {
+            Some(echo) => save::save_with_echo(response, path, &echo),
+            None => save::save(response, path).map(|()| None),
+        };
+        match saved {
+            Ok(None) => format!("Saved response to {}", path.display()),
+            Ok(Some(meta)) => format!(
+                "Saved response to {}, request noted in {}",
+                path.display(),
+                meta.display()
+            ),
+            Err(err) => format!("Save failed: {}", err),
+        }
+    }
+
+    /// Rebuilds the response pane text, after the trace of a verbose send,
+    /// and finds the search matches in it.
+    fn refresh_response_text(&mut self) {
+        self.render_response_text();
+        if let Some(trace) = &self.trace {
+            self.response_text = format!("{}\n\n{}", trace, self.response_text.as_str()).into();
+            self.response_table = None;
+            if !self.diff_marks.is_empty() {
+                let lines = trace.lines().count() + 1;
+                self.diff_marks
+                    .splice(0..0, std::iter::repeat_n(' ', lines));
+            }
+        }
+        if !self.search.pattern.is_empty() {
+            self.search.run(&self.response_text, self.response_scroll);
+        }
+    }
+
+    /// Searches the response pane for the pattern as now set up, scrolling
+    /// to the first match from the top of the pane, and tells how it went.
+    fn run_search(&mut self) -> String {
+        if self.search.pattern.is_empty() {
+            self.search.matches.clear();
+            return format!("Search: {}, type / to search", self.search.mode());
+        }
+        self.search.run(&self.response_text, self.response_scroll);
+        if let Some(line) = self.search.line() {
+            self.response_scroll = line;
+        }
+        self.search_status()
+    }
+
+    fn search_status(&self) -> String {
+        let search = &self.search;
+        match (&search.error, search.matches.len()) {
+            (Some(err), _) => format!("Invalid regex /{}/: {}", search.pattern, err),
+            (None, 0) => format!("No matches for '{}' ({})", search.pattern, search.mode()),
+            (None, count) => format!("Match {} of {}", search.current + 1, count),
+        }
+    }
+
+    /// Rebuilds the response pane text from the response or the chunks
+    /// received so far.
+    fn render_response_text(&mut self) {
+        self.highlight_line = None;
+        self.response_table = None;
+        self.diff_marks.clear();
+        self.baseline_changes = None;
+        self.diffed_expected = false;
+        self.decoded_base64 = None;
+        self.jsonp_callback = None;
+        let streamed = self.in_flight.is_some() || self.show_timestamps;
+        if streamed && !self.chunks.is_empty() {
+            self.response_text = stream::render(&self.chunks, self.show_timestamps).into();
+        } else if let Some(response) = &self.response {
+            if let Some(name) = save::attachment_name(response) {
+                self.response_text = format!(
+                    "Attachment: {} ({} bytes)\n\nCtrl+S: save it",
+                    name,
+                    response.bytes().len()
+                )
+                .into();
+                return;
+            }
+            if self.show_base64 {
+                match binary::decode(&response.body) {
+                    Ok(bytes) => {
+                        self.decoded_base64 = Some(bytes.len());
+                        self.response_text = binary::preview(&bytes).into();
+                        return;
+                    }
+                    Err(err) => {
+                        self.show_base64 = false;
+                        self.status = Some(format!("Response body is not base64: {}", err));
+                    }
+                }
+            }
+            let mut text = response.display_text();
+            self.jsonp_callback =
+                response::jsonp(&response.body).map(|(callback, _)| callback.to_string());
+            if self.sort_keys && !response.is_problem() {
+                text = response::sorted_json(&text).unwrap_or(text);
+            }
+            self.response_text = text.into();
+            self.response_table = response.table();
+
+            // What the request is documented to return beats what it once did
+            let baseline = self.response_request.as_ref().and_then(|request| {
+                if request.expected.is_empty() {
+                    self.baselines.get(request)
+                } else {
+                    Some(request.expected.as_str())
+                }
+            });
+            if let Some(baseline) = baseline {
+                self.diffed_expected = self
+                    .response_request
+                    .as_ref()
+                    .is_some_and(|request| !request.expected.is_empty());
+                // Sorted the same way, key order alone isn't a difference
+                let baseline = self
+                    .sort_keys
+                    .then(|| response::sorted_json(baseline))
+                    .flatten()
+                    .unwrap_or_else(|| baseline.to_string());
+                let diff = baseline::diff(&baseline, self.response_text.as_str());
+                let added = diff.iter().filter(|(mark, _)| *mark == '+').count();
+                let removed = diff.iter().filter(|(mark, _)| *mark == '-').count();
+                self.baseline_changes = Some((added, removed));
+                if added + removed > 0 && self.show_baseline_diff {
+                    self.response_table = None;
+                    self.diff_marks = diff.iter().map(|(mark, _)| *mark).collect();
+                    self.response_text = diff
+                        .iter()
+                        .map(|(mark, line)| format!("{} {}", mark, line))
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                        .into();
+                    return;
+                }
+            }
+
+            if let Some(depth) = self.fold_depth.filter(|_| !response.is_problem()) {
+                if let Some(folded) = response::folded_json(self.response_text.as_str(), depth) {
+                    self.response_text = folded.into();
+                }
+            }
+        }
+    }
+
+    /// Shows JSON responses folded below `depth` levels, or fully expanded.
+    fn fold(&mut self, depth: Option<usize>) {
+        self.fold_depth = depth;
+        self.response_scroll = 0;
+        self.refresh_response_text();
+    }
+
+    /// Pins the response on screen as the baseline later responses to the
+    /// same request are compared with.
+    fn pin_baseline(&mut self) {
+        let (Some(response), Some(request)) = (&self.response, &self.response_request) else {
+            self.status = Some("No response to pin as baseline".into());
+            return;
+        };
+        let text = response.display_text();
+        self.status = Some(match self.baselines.pin(request, &text) {
+            Ok(()) => "Response pinned as the baseline for this request".into(),
+            Err(err) => format!("Baseline not saved: {}", err),
+        });
+        self.refresh_response_text();
+    }
+
+    /// The text to open in the external editor for `target`, and the file
+    /// extension to give it.
+    pub fn edit_text(&self, target: EditTarget) -> (String, &'static str) {
+        let (text, content_type) = match target {
+            EditTarget::Body => (
+                self.request.body.clone(),
+                self.request.header("content-type"),
+            ),
+            // A first expected response starts out as the actual one
+            EditTarget::Expected => {
+                let response = self.response.as_ref();
+                let text = if self.request.expected.is_empty() {
+                    response.map(Response::display_text).unwrap_or_default()
+                } else {
+                    self.request.expected.clone()
+                };
+                (text, response.and_then(|r| r.header("content-type")))
+            }
+        };
+        let extension = match save::extension_for(content_type.unwrap_or_default()) {
+            "bin" => "txt",
+            extension => extension,
+        };
+        (text, extension)
+    }
+
+    /// Takes the body or expected response back from the external editor.
+    pub fn finish_edit(&mut self, target: EditTarget, result: Result<String, String>) {
+        let name = match target {
+            EditTarget::Body => "Body",
+            EditTarget::Expected => "Expected response",
+        };
+        self.status = Some(match result {
+            Ok(text) => {
+                // Editors end the file with a newline the text didn't have
+                let text = text.strip_suffix('\n').unwrap_or(&text).to_string();
+                match target {
+                    EditTarget::Body => {
+                        self.request.body = text;
+                        self.body_cursor = None;
+                        self.focus = Focus::Body;
+                        self.options_mode = 1;
+                    }
+                    EditTarget::Expected => {
+                        // The response on screen is diffed against it at once
+                        if let Some(answered) = &mut self.response_request {
+                            if answered.same_exchange(&self.request) {
+                                answered.expected = text.clone();
+                            }
+                        }
+                        self.request.expected = text;
+                        self.show_baseline_diff = true;
+                        self.refresh_response_text();
+                    }
+                }
+                format!("{} updated from the editor", name)
+            }
+            Err(err) => format!("{} unchanged: {}", name, err),
+        });
+    }
+
+    /// Converts the body from a JSON object to form fields or back, and sets
+    /// the matching `Content-Type`. The body is left alone if it can't be
+    /// converted without losing data.
+    fn toggle_body_format(&mut self) {
+        let body = self.request.body.trim();
+        if body.is_empty() {
+            self.status = Some("Body is empty".into());
+            return;
+        }
+        let (result, content_type) = if body.starts_with('{') {
+            (form::from_json(body), "application/x-www-form-urlencoded")
+        } else {
+            (form::to_json(body), "application/json")
+        };
+        self.status = Some(match result {
+            Ok(converted) => {
+                self.request.body = converted;
+                self.body_cursor = None;
+                self.request.set_header("Content-Type", content_type);
+                format!("Body converted, Content-Type: {}", content_type)
+            }
+            Err(err) => format!("Body not converted: {}", err),
+        });
+    }
+
+    /// Switches the body between text and base64, which is sent as the
+    /// bytes it decodes to. Binary bodies stay base64.
+    fn toggle_body_base64(&mut self) {
+        let request = &mut self.request;
+        self.status = Some(if request.body.trim().is_empty() {
+            request.body_base64 = !request.body_base64;
+            if request.body_base64 {
+                "Body is base64, sent decoded".into()
+            } else {
+                "Body is text, sent as typed".into()
+            }
+        } else if !request.body_base64 {
+            request.body = binary::encode(request.body.as_bytes());
+            request.body_base64 = true;
+            "Body encoded as base64, sent decoded".into()
+        } else {
+            match binary::decode(&request.body).map(String::from_utf8) {
+                Ok(Ok(text)) => {
+                    request.body = text;
+                    request.body_base64 = false;
+                    "Body decoded from base64, sent as typed".into()
+                }
+                Ok(Err(err)) => format!(
+                    "Body kept as base64: it decodes to {} bytes of binary",
+                    err.as_bytes().len()
+                ),
+                Err(err) => format!("Body not decoded: {}", err),
+            }
+        });
+        self.body_cursor = None;
+    }
+
+    /// Re-indents the body as XML or JSON, going by its `Content-Type` or
+    /// else its first character. With `check_only` the body is only
+    /// checked for being well-formed.
+    fn format_body(&mut self, check_only: bool) {
+        let body = self.request.body.trim();
+        if body.is_empty() {
+            self.status = Some("Body is empty".into());
+            return;
+        }
+        let is_xml = match self.request.header("content-type") {
+            Some(content_type) => xml::is_xml(content_type),
+            None => body.starts_with('<'),
+        };
+        let (kind, result) = if is_xml {
+            ("XML", xml::pretty(body))
+        } else {
+            let result = serde_json::from_str::<serde_json::Value>(body)
+                .and_then(|value| serde_json::to_string_pretty(&value))
+                .map_err(|e| e.to_string());
+            ("JSON", result)
+        };
+        self.status = Some(match result {
+            Ok(_) if check_only => format!("Body is well-formed {}", kind),
+            Ok(pretty) => {
+                self.request.body = pretty;
+                self.body_cursor = None;
+                format!("Body formatted as {}", kind)
+            }
+            Err(err) => format!("Body is not valid {}: {}", kind, err),
+        });
+    }
+
+    /// Whether `field` of the request differs from the loaded or saved
+    /// version. A new request has nothing to differ from.
+    pub fn modified<T: PartialEq>(&self, field: impl Fn(&Request) -> &T) -> bool {
+        self.saved
+            .as_ref()
+            .is_some_and(|saved| field(saved) != field(&self.request))
+    }
+
+    /// Cookies the active jar would send to the current URL.
+    pub fn jar_cookies(&self) -> Option<String> {
+        let url = Url::parse(&self.request.url).ok()?;
+        let header = self.jars.cookies(&url)?;
+        header.to_str().ok().map(String::from)
+    }
+
+    /// Saves the cookie jars when the config asks for them to be kept
+    /// between sessions.
+    pub fn persist_jars(&self) -> Result<(), String> {
+        if !self.config.persist_cookie_jars {
+            return Ok(());
+        }
+        self.jars.save()
+    }
+
+    /// Summary of the configured keys found in the last response, if any.
+    pub fn response_summary(&self) -> Option<String> {
+        self.response
+            .as_ref()
+            .and_then(|response| response.summary(&self.config.summary_keys))
+    }
+
+    fn open_prompt(&mut self, label: impl Into<String>, initial: &str, action: PromptAction) {
+        self.status = None;
+        self.prompt = Some(Prompt {
+            label: label.into(),
+            input: initial.to_string(),
+            action,
+        });
+    }
+
+    fn on_prompt_key(&mut self, key: KeyEvent) {
+        let Some(prompt) = self.prompt.as_mut() else {
+            return;
+        };
+        match key.code {
+            KeyCode::Esc => {
+                if prompt.action == PromptAction::PromptValue {
+                    self.pending_send = None;
+                    self.status = Some("Request not sent".into());
+                }
+                self.prompt = None;
+            }
+            KeyCode::Enter => {
+                if let Some(prompt) = self.prompt.take() {
+                    self.run_prompt(prompt.action, prompt.input.trim());
+                }
+            }
+            KeyCode::Char(c) => prompt.input.push(c),
+            KeyCode::Backspace => {
+                prompt.input.pop();
+            }
+            _ => {}
+        }
+    }
+
+    fn run_prompt(&mut self, action: PromptAction, input: &str) {
+        self.status = Some(match action {
+            PromptAction::PromptValue => {
+                if let Some(pending) = self.pending_send.as_mut() {
+                    let label = pending.labels.remove(0);
+                    pending.answers.push((label, input.to_string()));
+                }
+                self.status = None;
+                self.ask_next_value();
+                return;
+            }
+            PromptAction::CookieOverride => {
+                self.request.cookies = input.to_string();
+                if input.is_empty() {
+                    "Cookie override cleared, the jar is used again".into()
+                } else {
+                    "Cookie override set, the jar is bypassed for this request".into()
+                }
+            }
+            PromptAction::Search => {
+                self.search.pattern = input.to_string();
+                self.run_search()
+            }
+            PromptAction::LoadDotenv => match dotenv::load(Path::new(input)) {
+                Ok(loaded) if loaded.issues.is_empty() => {
+                    format!("Loaded {} variables from {}", loaded.count, input)
+                }
+                Ok(loaded) => format!(
+                    "Loaded {} variables from {}, skipped {}",
+                    loaded.count,
+                    input,
+                    loaded.issues.join("; ")
+                ),
+                Err(err) => format!("Variables not loaded: {}", err),
+            },
+            PromptAction::CookieJar if input.trim().is_empty() => "Cookie jar not changed".into(),
+            PromptAction::CookieJar => {
+                let name = input.trim();
+                let switched = if self.jars.switch(name) {
+                    format!("Created cookie jar '{}' and switched to it", name)
+                } else {
+                    format!("Switched to cookie jar '{}'", name)
+                };
+                match self.persist_jars() {
+                    Ok(()) => switched,
+                    Err(err) => format!("{}; jars not saved: {}", switched, err),
+                }
+            }
+            PromptAction::HostOverride => {
+                if let Some(err) = http::host_override_error(input).filter(|_| !input.is_empty()) {
+                    format!("Host override not changed: {}", err)
+                } else {
+                    self.request.host = input.to_string();
+                    let current = self.request.sni.clone();
+                    self.open_prompt(
+                        "TLS SNI override (empty: from the URL)",
+                        &current,
+                        PromptAction::SniOverride,
+                    );
+                    return;
+                }
+            }
+            PromptAction::SniOverride => {
+                if let Some(err) = http::sni_error(input).filter(|_| !input.is_empty()) {
+                    format!("SNI override not changed: {}", err)
+                } else {
+                    self.request.sni = input.to_string();
+                    if self.request.host.is_empty() && input.is_empty() {
+                        "Host and SNI come from the URL again".into()
+                    } else {
+                        "Overrides set, see the wire headers (W)".into()
+                    }
+                }
+            }
+            PromptAction::Batch => match batch::read_urls(input) {
+                Ok(urls) => {
+                    self.batch_urls = urls;
+                    let concurrency = self.config.batch_concurrency.to_string();
+                    self.open_prompt(
+                        "Requests at a time",
+                        &concurrency,
+                        PromptAction::BatchConcurrency,
+                    );
+                    return;
+                }
+                Err(err) => format!("Batch not started: {}", err),
+            },
+            PromptAction::BatchConcurrency => {
+                let urls = std::mem::take(&mut self.batch_urls);
+                match input.parse::<usize>() {
+                    Ok(concurrency) if concurrency > 0 => {
+                        let started = Batch::start(
+                            |target| self.client_for(target, false),
+                            &self.config,
+                            self.auth.clone(),
+                            &self.request,
+                            urls,
+                            concurrency,
+                        );
+                        match started {
+                            Ok(batch) => {
+                                let count = batch.urls.len();
+                                self.batch = Some(batch);
+                                format!(
+                                    "Batch of {} URLs started, {} at a time (Esc: cancel)",
+                                    count, concurrency
+                                )
+                            }
+                            Err(err) => format!("Batch not started: {}", err),
+                        }
+                    }
+                    _ => format!("Batch not started: '{}' is not a positive number", input),
+                }
+            }
+            PromptAction::SaveResponse => self.save_response(Path::new(input)),
+            PromptAction::ExportMetadata => match &self.response {
+                Some(response) => {
+                    let text =
+                        serde_json::to_string_pretty(&response.metadata()).unwrap_or_default();
+                    match fs::write(input, text + "\n") {
+                        Ok(()) => format!("Saved response metadata to {}", input),
+                        Err(err) => format!("Metadata not saved: {}", err),
+                    }
+                }
+                None => "No response to export".into(),
+            },
+            PromptAction::ExportHttp => match fs::write(input, httpfile::export(&self.request)) {
+                Ok(()) => {
+                    self.saved = Some(self.request.clone());
+                    format!("Exported request to {}", input)
+                }
+                Err(err) => format!("Export failed: {}", err),
+            },
+            PromptAction::ImportHttp => match fs::read_to_string(input)
+                .map_err(|e| e.to_string())
+                .and_then(|text| httpfile::import(&text))
+            {
+                Ok(mut request) => {
+                    request.sync_params();
+                    self.saved = Some(request.clone());
+                    self.request = request;
+                    self.decoded_query = None;
+                    format!("Imported request from {}", input)
+                }
+                Err(err) => format!("Import failed: {}", err),
+            },
+        });
+    }
+}
+
+/// How a verbose send went: the connection, then the requests and
+/// responses that went over it.
+fn verbose_trace(result: &Result<Response, String>) -> String {
+    let mut lines = vec!["* Verbose send, over a connection of its own".to_string()];
+    if let Ok(response) = result {
+        if let Some(connection) = response.connection {
+            lines.push(format!(
+                "* Connected to {} from {}",
+                connection.remote, connection.local
+            ));
+        }
+        if let Some(size) = response.certificate_size {
+            lines.push(format!(
+                "* TLS handshake done, server certificate of {} bytes (the TLS library doesn't report the negotiated version or cipher)",
+                size
+            ));
+        }
+    }
+    lines.extend(trace::finish());
+    lines.join("\n")
+}
+
+/// Builds the clients with and without the cookie jar.
+fn clients(config: &Config, jar: &Arc<Jars>) -> Result<(Client, Client), String> {
+    Ok((
+        http::client(config, Some(Arc::clone(jar)))?,
+        http::client(config, None)?,
+    ))
+}
+
+/// Flips the URL between `http://` and `https://`, prepending `https://` when
+/// it has no scheme yet. Everything after the scheme is kept as-is.
+fn toggle_scheme(url: &str) -> String {
+    let lower = url.to_ascii_lowercase();
+    if lower.starts_with("https://") {
+        format!("http://{}", &url["https://".len()..])
+    } else if lower.starts_with("http://") {
+        format!("https://{}", &url["http://".len()..])
+    } else if url.contains("://") {
+        url.to_string()
+    } else {
+        format!("https://{}", url)
+    }
+}
@@ -0,0 +1,68 @@
+//! Responses pinned as the expected ("golden") output of a request, stored
+//! as `baselines.json` in the config directory, and line diffs against them.
+//!
+//! Like recordings, baselines are keyed by the request as typed.
+
+use serde::{Deserialize, Serialize};
+use similar::{ChangeTag, TextDiff};
+
+use crate::{request::Request, store};
+
+const FILE: &str = "baselines.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Baseline {
+    request: Request,
+    text: String, // The response as shown in the response pane
+}
+
+#[derive(Debug, Default)]
+pub struct Baselines {
+    entries: Vec<Baseline>,
+}
+
+/// One line of a diff: `' '` unchanged, `'-'` only in the baseline, `'+'`
+/// only in the new response.
+pub type DiffLine = (char, String);
+
+impl Baselines {
+    /// Loads the baselines file; a missing file means no baselines.
+    pub fn load() -> Result<Self, String> {
+        store::load(FILE).map(|entries| Self { entries })
+    }
+
+    /// Pins `text` as the baseline for `request`, replacing any earlier one,
+    /// and writes the file.
+    pub fn pin(&mut self, request: &Request, text: &str) -> Result<(), String> {
+        self.entries
+            .retain(|entry| !entry.request.same_exchange(request));
+        self.entries.push(Baseline {
+            request: request.clone(),
+            text: text.to_string(),
+        });
+        store::save(FILE, &self.entries)
+    }
+
+    /// The baseline text for `request`, if one was pinned.
+    pub fn get(&self, request: &Request) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|entry| entry.request.same_exchange(request))
+            .map(|entry| entry.text.as_str())
+    }
+}
+
+/// Line diff from `baseline` to `text`.
+pub fn diff(baseline: &str, text: &str) -> Vec<DiffLine> {
+    TextDiff::from_lines(baseline, text)
+        .iter_all_changes()
+        .map(|change| {
+            let mark = match change.tag() {
+                ChangeTag::Equal => ' ',
+                ChangeTag::Delete => '-',
+                ChangeTag::Insert => '+',
+            };
+            (mark, change.value().trim_end_matches('\n').to_string())
+        })
+        .collect()
+}
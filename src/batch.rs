@@ -0,0 +1,167 @@
+//! Sending the current request to every URL listed in a file.
+//!
+//! Requests are sent the way a single one is, by a limited number of
+//! background threads, and report back over a channel so the results table
+//! fills in as they complete.
+
+use std::{
+    fs,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc::{self, Receiver, TryRecvError},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use reqwest::blocking::Client;
+
+use crate::{config::Config, http, oauth::TokenSource, request::Request, template};
+
+/// Requests allowed in flight at the same time, unless configured.
+pub const DEFAULT_CONCURRENCY: usize = 4;
+
+/// How one URL of the batch went: status, time taken and body bytes
+/// received, or the error.
+pub type Outcome = Result<(u16, Duration, usize), String>;
+
+pub struct Batch {
+    pub request: Request, // Sent to every URL, as given
+    pub urls: Vec<String>,
+    pub results: Vec<Option<Outcome>>, // Indexed like `urls`, `None` until done
+    pub finished: bool,
+    pub cancelled: bool,
+    pub concurrency: usize, // Requests allowed in flight at the same time
+    events: Receiver<(usize, Outcome)>,
+    cancel: Arc<AtomicBool>,
+    in_flight: Arc<AtomicUsize>,
+}
+
+/// Reads URLs from `path`, one per line, skipping blank lines and `#`
+/// comments.
+pub fn read_urls(path: &str) -> Result<Vec<String>, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    let urls: Vec<String> = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(String::from)
+        .collect();
+    if urls.is_empty() {
+        return Err(format!("{}: no URLs found", path));
+    }
+    Ok(urls)
+}
+
+impl Batch {
+    /// Starts sending `request` to each of `urls` in place of its own, at
+    /// most `concurrency` at a time, each with the client `client_for` gives
+    /// it. `auth` provides the token for `{{oauth2.token}}`. Requests with
+    /// `{{prompt:...}}` placeholders aren't sent, as there's no one to
+    /// answer them for every URL.
+    pub fn start(
+        client_for: impl Fn(&Request) -> Result<Client, String>,
+        config: &Config,
+        auth: Option<Arc<TokenSource>>,
+        request: &Request,
+        urls: Vec<String>,
+        concurrency: usize,
+    ) -> Result<Self, String> {
+        let concurrency = concurrency.max(1);
+        let targets: Vec<Request> = urls
+            .iter()
+            .map(|url| Request {
+                url: url.clone(),
+                ..request.clone()
+            })
+            .collect();
+        if targets
+            .iter()
+            .any(|target| !template::prompt_labels(target).is_empty())
+        {
+            return Err("{{prompt:...}} placeholders can't be answered for every URL".into());
+        }
+        let targets = targets
+            .into_iter()
+            .map(|target| client_for(&target).map(|client| (target, client)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let (tx, rx) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let targets = Arc::new(targets);
+        let next = Arc::new(AtomicUsize::new(0));
+        for _ in 0..concurrency.min(targets.len()) {
+            let (config, auth, tx) = (config.clone(), auth.clone(), tx.clone());
+            let (targets, next) = (Arc::clone(&targets), Arc::clone(&next));
+            let (cancelled, running) = (Arc::clone(&cancel), Arc::clone(&in_flight));
+            thread::spawn(move || loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                if index >= targets.len() || cancelled.load(Ordering::Relaxed) {
+                    break;
+                }
+                running.fetch_add(1, Ordering::Relaxed);
+                let (target, client) = &targets[index];
+                let outcome = http::execute(client, &config, target, auth.as_deref())
+                    .map(|response| (response.status, response.elapsed, response.bytes().len()));
+                running.fetch_sub(1, Ordering::Relaxed);
+                if cancelled.load(Ordering::Relaxed) || tx.send((index, outcome)).is_err() {
+                    break;
+                }
+            });
+        }
+
+        Ok(Self {
+            request: request.clone(),
+            results: vec![None; urls.len()],
+            urls,
+            finished: false,
+            cancelled: false,
+            concurrency,
+            events: rx,
+            cancel,
+            in_flight,
+        })
+    }
+
+    /// Collects results that arrived since the last call, returning the
+    /// indices of their URLs.
+    pub fn poll(&mut self) -> Vec<usize> {
+        let mut arrived = Vec::new();
+        loop {
+            match self.events.try_recv() {
+                Ok((index, outcome)) => {
+                    self.results[index] = Some(outcome);
+                    arrived.push(index);
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.finished = true;
+                    break;
+                }
+            }
+        }
+        arrived
+    }
+
+    /// Stops starting new requests; the ones in flight are left to finish
+    /// unseen.
+    pub fn cancel(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+        self.cancelled = true;
+    }
+
+    /// Requests sent and not answered yet.
+    pub fn in_flight(&self) -> usize {
+        if self.finished || self.cancelled {
+            0
+        } else {
+            self.in_flight.load(Ordering::Relaxed)
+        }
+    }
+
+    pub fn completed(&self) -> usize {
+        self.results.iter().filter(|r| r.is_some()).count()
+    }
+}
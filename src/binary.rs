@@ -0,0 +1,78 @@
+//! Base64 for bodies that carry binary data: request bodies edited as
+//! base64 and sent as the bytes it stands for, and responses wrapping
+//! binary in base64.
+
+use base64::{
+    alphabet,
+    engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig},
+    Engine,
+};
+
+const CONFIG: GeneralPurposeConfig =
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent);
+const STANDARD: GeneralPurpose = GeneralPurpose::new(&alphabet::STANDARD, CONFIG);
+const URL_SAFE: GeneralPurpose = GeneralPurpose::new(&alphabet::URL_SAFE, CONFIG);
+
+/// Bytes a preview shows before leaving the rest out.
+const PREVIEW_BYTES: usize = 4096;
+
+pub fn encode(bytes: &[u8]) -> String {
+    STANDARD.encode(bytes)
+}
+
+/// Decodes standard or URL-safe base64, padded or not. Whitespace, such as
+/// line breaks every 76 characters, and the quotes of a JSON string are
+/// ignored.
+pub fn decode(text: &str) -> Result<Vec<u8>, String> {
+    let text = text.trim();
+    let text = text
+        .strip_prefix('"')
+        .and_then(|text| text.strip_suffix('"'))
+        .unwrap_or(text);
+    let compact: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+    if compact.is_empty() {
+        return Err("nothing to decode".into());
+    }
+    STANDARD
+        .decode(&compact)
+        .or_else(|_| URL_SAFE.decode(&compact))
+        .map_err(|err| err.to_string())
+}
+
+/// The size of `bytes` and then the bytes: as text when they are UTF-8
+/// without control characters other than line breaks and tabs, as a hex
+/// dump otherwise.
+pub fn preview(bytes: &[u8]) -> String {
+    let text = std::str::from_utf8(bytes).ok().filter(|text| {
+        !text
+            .chars()
+            .any(|c| c.is_control() && !matches!(c, '\n' | '\r' | '\t'))
+    });
+    if let Some(text) = text {
+        return format!("{} bytes, UTF-8 text:\n\n{}", bytes.len(), text);
+    }
+    let mut out = format!("{} bytes, binary:\n\n", bytes.len());
+    for (i, row) in bytes[..bytes.len().min(PREVIEW_BYTES)]
+        .chunks(16)
+        .enumerate()
+    {
+        let hex: Vec<String> = row.iter().map(|byte| format!("{:02x}", byte)).collect();
+        let ascii: String = row
+            .iter()
+            .map(|&byte| match byte {
+                0x20..=0x7e => byte as char,
+                _ => '.',
+            })
+            .collect();
+        out.push_str(&format!(
+            "{:08x}  {:<47}  |{}|\n",
+            i * 16,
+            hex.join(" "),
+            ascii
+        ));
+    }
+    if bytes.len() > PREVIEW_BYTES {
+        out.push_str(&format!("... {} more bytes\n", bytes.len() - PREVIEW_BYTES));
+    }
+    out
+}
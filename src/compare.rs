@@ -0,0 +1,118 @@
+//! A/B comparison: a copy of the request (A) sent together with the
+//! request as edited since (B), the two responses shown side by side.
+
+use std::sync::mpsc::{Receiver, TryRecvError};
+
+use crate::{
+    baseline::{self, DiffLine},
+    http::Event,
+    request::Request,
+    response::Response,
+};
+
+pub struct Comparison {
+    pub a: Request,
+    /// B as it was last sent, `None` until then.
+    pub b: Option<Request>,
+    /// A's and B's outcome, `None` while on the way.
+    pub results: [Option<Result<Response, String>>; 2],
+    /// A's and B's lines, aligned so unchanged lines sit side by side.
+    /// Filled in once both are back.
+    pub columns: Option<[Vec<DiffLine>; 2]>,
+    events: [Option<Receiver<Event>>; 2],
+}
+
+impl Comparison {
+    pub fn new(a: Request) -> Self {
+        Self {
+            a,
+            b: None,
+            results: [None, None],
+            columns: None,
+            events: [None, None],
+        }
+    }
+
+    /// Waits for the responses to A and `b` from `events`, dropping any
+    /// earlier ones.
+    pub fn sent(&mut self, b: Request, events: [Receiver<Event>; 2]) {
+        self.b = Some(b);
+        self.results = [None, None];
+        self.columns = None;
+        self.events = events.map(Some);
+    }
+
+    /// Picks up finished responses, then diffs them once both are in.
+    /// Returns which of A (0) and B (1) finished.
+    pub fn poll(&mut self) -> Vec<usize> {
+        let mut arrived = Vec::new();
+        for (side, (events, result)) in self.events.iter_mut().zip(&mut self.results).enumerate() {
+            let Some(rx) = events else { continue };
+            loop {
+                match rx.try_recv() {
+                    Ok(Event::Done(done)) => *result = Some(done),
+                    Ok(_) => continue,
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => {
+                        *result = Some(Err("Request thread stopped unexpectedly".into()))
+                    }
+                }
+                *events = None;
+                arrived.push(side);
+                break;
+            }
+        }
+
+        if let (None, [Some(a), Some(b)]) = (&self.columns, &self.results) {
+            let diff = baseline::diff(&text(a), &text(b));
+            self.columns = Some(side_by_side(&diff));
+        }
+        arrived
+    }
+
+    /// Names of the request fields B changed from A.
+    pub fn changed_fields(&self) -> Vec<&'static str> {
+        let Some(b) = &self.b else {
+            return Vec::new();
+        };
+        let a = &self.a;
+        [
+            ("method", a.method != b.method),
+            ("url", a.url != b.url),
+            ("headers", a.headers != b.headers),
+            ("body", a.body != b.body),
+            ("cookies", a.cookies != b.cookies),
+        ]
+        .into_iter()
+        .filter_map(|(name, changed)| changed.then_some(name))
+        .collect()
+    }
+}
+
+/// What the response pane would show for `result`.
+fn text(result: &Result<Response, String>) -> String {
+    match result {
+        Ok(response) => response.display_text(),
+        Err(err) => err.clone(),
+    }
+}
+
+/// Splits a diff into left (unchanged and removed) and right (unchanged and
+/// added) columns, padding with blank lines so unchanged lines line up.
+fn side_by_side(diff: &[DiffLine]) -> [Vec<DiffLine>; 2] {
+    let (mut left, mut right) = (Vec::new(), Vec::new());
+    for (mark, line) in diff {
+        match mark {
+            '-' => left.push((*mark, line.clone())),
+            '+' => right.push((*mark, line.clone())),
+            _ => {
+                let rows = left.len().max(right.len());
+                left.resize(rows, (' ', String::new()));
+                right.resize(rows, (' ', String::new()));
+                left.push((*mark, line.clone()));
+                right.push((*mark, line.clone()));
+            }
+        }
+    }
+    [left, right]
+}
@@ -0,0 +1,407 @@
+//! User configuration, read from `config.toml` in the LazyCurl config
+//! directory (`$XDG_CONFIG_HOME/lazycurl` or `~/.config/lazycurl`, unless
+//! `--dir` or `$LAZYCURL_DIR` name another one), then from any
+//! `.lazycurl.toml` in the current directory and its parents. Closer files
+//! override settings of the ones before them; tables are merged, other
+//! values replaced.
+//!
+//! Every setting is optional; no files means the defaults.
+
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{batch, http};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Seconds to wait for a response before giving up.
+    pub timeout_secs: u64,
+    /// Top-level JSON keys shown in the response title when present.
+    pub summary_keys: Vec<String>,
+    /// JSON keys the response jump-to-error key looks for.
+    pub error_keys: Vec<String>,
+    /// Enter in the body editor sends the request instead of inserting a
+    /// newline. Ctrl+Enter sends from anywhere either way.
+    pub body_enter_sends: bool,
+    /// Send `Expect: 100-continue` with request bodies. Off by default as
+    /// some servers mishandle it.
+    pub expect_continue: bool,
+    /// Move focus to the response pane when a response arrives, except
+    /// while polling.
+    pub focus_response: bool,
+    /// Requests a batch run (Ctrl+B) sends at the same time, unless another
+    /// number is given when starting it.
+    pub batch_concurrency: usize,
+    /// Keep the cookie jars (Alt+K) and their cookies between sessions.
+    pub persist_cookie_jars: bool,
+    /// Seconds between a response and the next send while polling (F8).
+    pub poll_interval_secs: u64,
+    /// Note the request that produced a saved response: a comment at the
+    /// top of text files, a `.meta.json` file next to JSON, XML and binary
+    /// ones.
+    pub echo_request_on_save: bool,
+    /// PEM file of CA certificates to trust on top of the system ones.
+    pub ca_bundle: Option<PathBuf>,
+    /// Clears the response pane, leaving the request as it is, e.g.
+    /// `"Ctrl+Alt+L"`. Keys the app already binds aren't taken.
+    pub clear_response_key: Shortcut,
+    pub navigation: Navigation,
+    /// Where `{{oauth2.token}}` gets its token from.
+    pub oauth2: Option<OAuth2>,
+    /// Body snippets offered by Ctrl+N in the body editor. Setting this
+    /// replaces the built-in ones.
+    pub snippets: Vec<Snippet>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            timeout_secs: 30,
+            summary_keys: vec!["error".into(), "message".into(), "id".into()],
+            error_keys: vec!["error".into(), "errors".into(), "message".into()],
+            body_enter_sends: false,
+            expect_continue: false,
+            focus_response: false,
+            batch_concurrency: batch::DEFAULT_CONCURRENCY,
+            persist_cookie_jars: false,
+            poll_interval_secs: 5,
+            echo_request_on_save: false,
+            ca_bundle: None,
+            clear_response_key: Shortcut {
+                ctrl: false,
+                alt: true,
+                key: 'c',
+            },
+            navigation: Navigation::default(),
+            oauth2: None,
+            snippets: vec![
+                Snippet::new("Object", "{${}}"),
+                Snippet::new("Array", "[${}]"),
+                Snippet::new(
+                    "JSON-RPC request",
+                    "{\"jsonrpc\": \"2.0\", \"method\": \"${method}\", \"params\": ${params}, \"id\": 1}",
+                ),
+            ],
+        }
+    }
+}
+
+/// OAuth2 client credentials grant settings. `client_secret` may be a
+/// `{{secret.name}}` placeholder.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OAuth2 {
+    pub token_url: String,
+    pub client_id: String,
+    pub client_secret: String,
+    pub scope: String,
+    /// On a 401, fetch a new token and send the request once more.
+    pub retry_on_401: bool,
+}
+
+impl Default for OAuth2 {
+    fn default() -> Self {
+        Self {
+            token_url: String::new(),
+            client_id: String::new(),
+            client_secret: String::new(),
+            scope: String::new(),
+            retry_on_401: true,
+        }
+    }
+}
+
+/// A piece of text the body editor can insert. `${...}` marks a field
+/// that Tab jumps to.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Snippet {
+    pub name: String,
+    pub text: String,
+}
+
+impl Snippet {
+    fn new(name: &str, text: &str) -> Self {
+        Self {
+            name: name.into(),
+            text: text.into(),
+        }
+    }
+}
+
+/// How the arrow and paging keys move through lists.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Navigation {
+    /// Move further per key press while an arrow key is held down.
+    pub accelerate: bool,
+    /// Presses of the same key closer together than this count as held.
+    pub repeat_window_ms: u64,
+    /// Rows moved by PageUp/PageDown.
+    pub page_size: usize,
+}
+
+impl Default for Navigation {
+    fn default() -> Self {
+        Self {
+            accelerate: true,
+            repeat_window_ms: 80,
+            page_size: 10,
+        }
+    }
+}
+
+/// Ctrl and Alt keys with a meaning of their own in the app.
+const BUILT_IN_SHORTCUTS: [&str; 22] = [
+    "Ctrl+B", "Ctrl+D", "Ctrl+E", "Ctrl+F", "Ctrl+G", "Ctrl+J", "Ctrl+K", "Ctrl+L", "Ctrl+N",
+    "Ctrl+O", "Ctrl+P", "Ctrl+R", "Ctrl+S", "Ctrl+T", "Ctrl+U", "Ctrl+W", "Ctrl+X", "Ctrl+Y",
+    "Alt+B", "Alt+E", "Alt+K", "Alt+V",
+];
+
+/// A letter or other character key pressed with Ctrl, Alt or both, written
+/// like `Ctrl+K`. Plain keys are left to typing.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Shortcut {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub key: char, // Lowercase
+}
+
+impl TryFrom<String> for Shortcut {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, String> {
+        let mut shortcut = Shortcut {
+            ctrl: false,
+            alt: false,
+            key: ' ',
+        };
+        let mut parts: Vec<&str> = text.split('+').map(str::trim).collect();
+        // A `+` key leaves an empty part after the last separator
+        let key = match parts.pop() {
+            Some("") if text.ends_with("++") => {
+                parts.pop();
+                "+"
+            }
+            Some(key) => key,
+            None => "",
+        };
+        for modifier in parts {
+            match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => shortcut.ctrl = true,
+                "alt" | "meta" => shortcut.alt = true,
+                _ => return Err(format!("unknown modifier '{}' in '{}'", modifier, text)),
+            }
+        }
+        let mut chars = key.chars();
+        shortcut.key = match (chars.next(), chars.next()) {
+            (Some(key), None) => key.to_ascii_lowercase(),
+            _ => return Err(format!("'{}' doesn't end in a single key", text)),
+        };
+        if !shortcut.ctrl && !shortcut.alt {
+            return Err(format!("'{}' needs Ctrl or Alt", text));
+        }
+        Ok(shortcut)
+    }
+}
+
+impl From<Shortcut> for String {
+    fn from(shortcut: Shortcut) -> Self {
+        shortcut.to_string()
+    }
+}
+
+impl std::fmt::Display for Shortcut {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.ctrl {
+            f.write_str("Ctrl+")?;
+        }
+        if self.alt {
+            f.write_str("Alt+")?;
+        }
+        write!(f, "{}", self.key.to_ascii_uppercase())
+    }
+}
+
+/// Directory given on the command line, which wins over the environment.
+static DIR_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Uses `dir` instead of the usual directory from now on.
+pub fn set_dir(dir: PathBuf) {
+    let _ = DIR_OVERRIDE.set(dir);
+}
+
+/// Directory holding the config file and anything else LazyCurl persists.
+pub fn dir() -> Option<PathBuf> {
+    if let Some(dir) = DIR_OVERRIDE.get() {
+        return Some(dir.clone());
+    }
+    if let Some(dir) = env::var_os("LAZYCURL_DIR").filter(|d| !d.is_empty()) {
+        return Some(PathBuf::from(dir));
+    }
+    if let Some(dir) = env::var_os("XDG_CONFIG_HOME").filter(|d| !d.is_empty()) {
+        return Some(PathBuf::from(dir).join("lazycurl"));
+    }
+    env::var_os("HOME").map(|home| PathBuf::from(home).join(".config").join("lazycurl"))
+}
+
+/// Project config file looked for in the current directory and its parents.
+pub const LOCAL_FILE: &str = ".lazycurl.toml";
+
+/// The config merged from its files.
+#[derive(Debug, Default)]
+pub struct Layered {
+    pub config: Config,
+    pub loaded: Vec<PathBuf>, // Files merged in, in order
+    /// Why the files that couldn't be used were skipped.
+    pub warnings: Vec<String>,
+}
+
+/// Loads the global config file and the project ones over it. A file that
+/// can't be read or isn't a valid config on its own is skipped whole.
+pub fn load() -> Layered {
+    let global = dir().map(|d| d.join("config.toml"));
+    let mut local: Vec<PathBuf> = env::current_dir()
+        .iter()
+        .flat_map(|cwd| cwd.ancestors())
+        .map(|dir| dir.join(LOCAL_FILE))
+        .collect();
+    local.reverse();
+
+    let mut layered = Layered::default();
+    let mut merged = toml::Table::new();
+    for path in global.into_iter().chain(local) {
+        match read_layer(&path) {
+            Ok(Some(table)) => {
+                merge(&mut merged, table);
+                layered.loaded.push(path);
+            }
+            Ok(None) => {}
+            Err(err) => layered
+                .warnings
+                .push(format!("{} skipped: {}", path.display(), err)),
+        }
+    }
+    match merged.try_into() {
+        Ok(config) => layered.config = config,
+        Err(err) => layered
+            .warnings
+            .push(format!("merged config ignored: {}", err)),
+    }
+    keep_built_in_keys(&mut layered);
+    layered
+}
+
+/// Puts back the default for a configured shortcut the app already binds,
+/// as it would be checked first and take the key away, and warns about it.
+fn keep_built_in_keys(layered: &mut Layered) {
+    let shortcut = layered.config.clear_response_key;
+    let taken = BUILT_IN_SHORTCUTS
+        .iter()
+        .any(|text| Shortcut::try_from(text.to_string()) == Ok(shortcut));
+    if taken {
+        let default = Config::default().clear_response_key;
+        layered.warnings.push(format!(
+            "clear_response_key {} is already bound, using {}",
+            shortcut, default
+        ));
+        layered.config.clear_response_key = default;
+    }
+}
+
+/// The settings in `path`, `None` if there is no such file.
+fn read_layer(path: &Path) -> Result<Option<toml::Table>, String> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err.to_string()),
+    };
+    let table: toml::Table = toml::from_str(&text).map_err(|e| e.to_string())?;
+    let config: Config = table.clone().try_into().map_err(|e| e.to_string())?;
+    if let Some(bundle) = &config.ca_bundle {
+        http::ca_certificates(bundle)?;
+    }
+    Ok(Some(table))
+}
+
+/// Sets the values of `layer` in `base`, merging the tables both have.
+fn merge(base: &mut toml::Table, layer: toml::Table) {
+    for (key, value) in layer {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(layer)) => merge(base, layer),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+impl Config {
+    /// Dotted names of the settings that differ between `self` and `other`,
+    /// e.g. `navigation.page_size`.
+    pub fn changes(&self, other: &Config) -> Vec<String> {
+        let (Ok(old), Ok(new)) = (serde_json::to_value(self), serde_json::to_value(other)) else {
+            return Vec::new();
+        };
+        let mut changed = Vec::new();
+        diff_values("", &old, &new, &mut changed);
+        changed
+    }
+}
+
+fn diff_values(prefix: &str, old: &Value, new: &Value, changed: &mut Vec<String>) {
+    match (old, new) {
+        (Value::Object(old), Value::Object(new)) => {
+            for (key, old_value) in old {
+                let name = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                match new.get(key) {
+                    Some(new_value) => diff_values(&name, old_value, new_value, changed),
+                    None => changed.push(name),
+                }
+            }
+        }
+        (old, new) if old != new => changed.push(prefix.to_string()),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shortcuts_the_app_binds_are_not_taken_over() {
+        let loaded = |text: &str| {
+            let mut layered = Layered {
+                config: toml::from_str(&format!("clear_response_key = {:?}", text)).unwrap(),
+                ..Layered::default()
+            };
+            keep_built_in_keys(&mut layered);
+            (
+                layered.config.clear_response_key.to_string(),
+                layered.warnings,
+            )
+        };
+        assert_eq!(
+            loaded("ctrl+s"),
+            (
+                "Alt+C".to_string(),
+                vec!["clear_response_key Ctrl+S is already bound, using Alt+C".to_string()]
+            )
+        );
+        assert_eq!(loaded("Alt+K").0, "Alt+C");
+        assert_eq!(loaded("Ctrl+Alt+L"), ("Ctrl+Alt+L".to_string(), Vec::new()));
+    }
+}
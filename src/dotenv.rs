@@ -0,0 +1,150 @@
+//! Variables loaded from `.env` files for the session, filled in for
+//! `{{NAME}}` placeholders.
+//!
+//! Lines follow the dotenv conventions: `NAME=value`, optionally after
+//! `export `; `#` starts a comment at the start of a line or after
+//! whitespace in an unquoted value; single-quoted values are taken as-is,
+//! double-quoted ones understand `\n`, `\t`, `\"` and `\\`. Quoted values
+//! may span lines.
+
+use std::{collections::BTreeMap, fs, path::Path, sync::RwLock};
+
+static VARIABLES: RwLock<BTreeMap<String, String>> = RwLock::new(BTreeMap::new());
+
+/// What loading a file gave: how many variables were set, and the lines
+/// skipped along with why.
+#[derive(Debug, Default, PartialEq)]
+pub struct Loaded {
+    pub count: usize,
+    pub issues: Vec<String>,
+}
+
+/// Value of the loaded variable `name`.
+pub fn get(name: &str) -> Option<String> {
+    VARIABLES.read().ok()?.get(name).cloned()
+}
+
+/// Sets the variables of the `.env` file at `path`, replacing loaded ones
+/// of the same name. Lines that don't parse are skipped and reported.
+pub fn load(path: &Path) -> Result<Loaded, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let (variables, issues) = parse(&text);
+    let count = variables.len();
+    let mut loaded = VARIABLES
+        .write()
+        .map_err(|_| "variables unavailable".to_string())?;
+    loaded.extend(variables);
+    Ok(Loaded { count, issues })
+}
+
+/// The `NAME=value` pairs of a `.env` document, and a message for each
+/// line that isn't one.
+pub fn parse(text: &str) -> (Vec<(String, String)>, Vec<String>) {
+    let mut variables = Vec::new();
+    let mut issues = Vec::new();
+    let mut lines = text.lines().enumerate();
+    while let Some((no, line)) = lines.next() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let Some((name, rest)) = line.split_once('=') else {
+            issues.push(format!("line {}: expected NAME=value", no + 1));
+            continue;
+        };
+        let name = name.trim();
+        if !is_name(name) {
+            issues.push(format!("line {}: invalid name '{}'", no + 1, name));
+            continue;
+        }
+
+        let rest = rest.trim_start();
+        let value = match rest.chars().next() {
+            Some(quote @ ('"' | '\'')) => {
+                // Up to the closing quote, on this line or a later one
+                let mut quoted = rest[1..].to_string();
+                let mut closed = closing_quote(&quoted, quote);
+                while closed.is_none() {
+                    let Some((_, next)) = lines.next() else { break };
+                    quoted.push('\n');
+                    quoted.push_str(next);
+                    closed = closing_quote(&quoted, quote);
+                }
+                let Some(end) = closed else {
+                    issues.push(format!("line {}: unterminated {} quote", no + 1, quote));
+                    continue;
+                };
+                let after = quoted[end + 1..].trim();
+                if !after.is_empty() && !after.starts_with('#') {
+                    issues.push(format!(
+                        "line {}: unexpected '{}' after the closing quote",
+                        no + 1,
+                        after
+                    ));
+                    continue;
+                }
+                if quote == '"' {
+                    unescape(&quoted[..end])
+                } else {
+                    quoted[..end].to_string()
+                }
+            }
+            _ => {
+                let value = match rest.find(" #").or_else(|| rest.find("\t#")) {
+                    Some(comment) => &rest[..comment],
+                    None => rest,
+                };
+                value.trim().to_string()
+            }
+        };
+        variables.push((name.to_string(), value));
+    }
+    (variables, issues)
+}
+
+fn is_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Byte offset of the quote closing `text`, skipping escaped ones in
+/// double-quoted values.
+fn closing_quote(text: &str, quote: char) -> Option<usize> {
+    let mut escaped = false;
+    for (i, c) in text.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quote == '"' => escaped = true,
+            c if c == quote => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn unescape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('r') => out.push('\r'),
+            Some(c @ ('"' | '\\')) => out.push(c),
+            Some(c) => {
+                out.push('\\');
+                out.push(c);
+            }
+            None => out.push('\\'),
+        }
+    }
+    out
+}
@@ -0,0 +1,92 @@
+//! Editing text in the user's own editor.
+
+use std::{
+    collections::hash_map::RandomState,
+    env,
+    fs::{self, File, OpenOptions},
+    hash::{BuildHasher, Hasher},
+    io::{self, Write},
+    path::PathBuf,
+    process::{Command, Stdio},
+    time::SystemTime,
+};
+
+/// Tries at picking a temporary file name nobody else has taken.
+const CREATE_ATTEMPTS: usize = 16;
+
+/// Opens `text` in `$VISUAL`, `$EDITOR` or `vi`, in a temporary file ending
+/// in `.extension`, and returns the file's contents once the editor exits.
+/// The terminal must be handed over to the editor before calling this.
+pub fn edit(text: &str, extension: &str) -> Result<String, String> {
+    // An empty variable counts as unset
+    let set = |name| env::var(name).ok().filter(|value| !value.trim().is_empty());
+    let command = set("VISUAL")
+        .or_else(|| set("EDITOR"))
+        .unwrap_or_else(|| "vi".into());
+    // Allows editors that need flags, like `code --wait`
+    let mut words = command.split_whitespace();
+    let program = words.next().unwrap_or("vi");
+
+    let (path, mut file) = create_temp(extension)?;
+    let written = file
+        .write_all(text.as_bytes())
+        .map_err(|e| format!("{}: {}", path.display(), e));
+    drop(file);
+    if let Err(err) = written {
+        let _ = fs::remove_file(&path);
+        return Err(err);
+    }
+
+    // Stdin may be a pipe that `--body -` already read to the end
+    let stdin = File::open("/dev/tty").map_or_else(|_| Stdio::inherit(), Stdio::from);
+    let status = Command::new(program)
+        .args(words)
+        .arg(&path)
+        .stdin(stdin)
+        .status()
+        .map_err(|e| format!("{}: {}", program, e));
+    let result = match status {
+        Ok(status) if status.success() => {
+            fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))
+        }
+        Ok(status) => Err(format!("{} exited with {}", program, status)),
+        Err(err) => Err(err),
+    };
+    let _ = fs::remove_file(&path);
+    result
+}
+
+/// Creates a new file with a random name in the temporary directory,
+/// readable only by the user. An existing file, or a link planted under the
+/// name, is never opened.
+fn create_temp(extension: &str) -> Result<(PathBuf, File), String> {
+    let mut last_error = None;
+    for _ in 0..CREATE_ATTEMPTS {
+        let path = env::temp_dir().join(format!("lazycurl-body-{:016x}.{}", random(), extension));
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        match options.open(&path) {
+            Ok(file) => return Ok((path, file)),
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => last_error = Some(err),
+            Err(err) => return Err(format!("{}: {}", path.display(), err)),
+        }
+    }
+    Err(format!(
+        "no free temporary file name: {}",
+        last_error.map_or_else(String::new, |err| err.to_string())
+    ))
+}
+
+/// A number that differs between calls and is hard to guess ahead.
+fn random() -> u64 {
+    // RandomState's keys come from the OS's random source once per thread
+    // and are bumped for each new state, so they differ between calls
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u32(std::process::id());
+    if let Ok(since) = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
+        hasher.write_u128(since.as_nanos());
+    }
+    hasher.finish()
+}
@@ -0,0 +1,130 @@
+//! Converting flat request bodies between a JSON object and
+//! `application/x-www-form-urlencoded` form.
+
+use serde_json::{Map, Value};
+
+use crate::query::hex_val;
+
+/// A JSON object body as form fields. Fails, naming the offending keys,
+/// when a value is an array, an object or `null`, since form fields can't
+/// hold those.
+pub fn from_json(body: &str) -> Result<String, String> {
+    let Ok(Value::Object(object)) = serde_json::from_str::<Value>(body) else {
+        return Err("body is not a JSON object".into());
+    };
+
+    let nested: Vec<&str> = object
+        .iter()
+        .filter(|(_, value)| matches!(value, Value::Array(_) | Value::Object(_) | Value::Null))
+        .map(|(key, _)| key.as_str())
+        .collect();
+    if !nested.is_empty() {
+        return Err(format!("can't form-encode {}", nested.join(", ")));
+    }
+
+    let pairs: Vec<String> = object
+        .iter()
+        .map(|(key, value)| {
+            let value = match value {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            };
+            format!("{}={}", encode(key), encode(&value))
+        })
+        .collect();
+    Ok(pairs.join("&"))
+}
+
+/// Form fields as a JSON object with string values. Fails, naming the
+/// offending keys, when a key repeats, since an object holds each key once.
+pub fn to_json(body: &str) -> Result<String, String> {
+    let mut object = Map::new();
+    let mut repeated = Vec::new();
+    for pair in body.trim().split('&').filter(|pair| !pair.is_empty()) {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let key = decode(key)?;
+        if object.contains_key(&key) {
+            if !repeated.contains(&key) {
+                repeated.push(key);
+            }
+            continue;
+        }
+        object.insert(key, Value::String(decode(value)?));
+    }
+    if !repeated.is_empty() {
+        return Err(format!(
+            "can't turn repeated {} into JSON",
+            repeated.join(", ")
+        ));
+    }
+    serde_json::to_string_pretty(&object).map_err(|e| e.to_string())
+}
+
+fn encode(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for b in text.bytes() {
+        match b {
+            b' ' => out.push('+'),
+            b if b.is_ascii_alphanumeric() || b"*-._".contains(&b) => out.push(b as char),
+            b => out.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    out
+}
+
+fn decode(text: &str) -> Result<String, String> {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' => {
+                let (Some(hi), Some(lo)) = (
+                    bytes.get(i + 1).copied().and_then(hex_val),
+                    bytes.get(i + 2).copied().and_then(hex_val),
+                ) else {
+                    return Err(format!("bad escape in {:?}", text));
+                };
+                out.push((hi << 4) | lo);
+                i += 2;
+            }
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8(out).map_err(|_| format!("{:?} is not UTF-8 once decoded", text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_objects_become_fields_and_back() {
+        let body = r#"{"name":"a b&c","count":3,"ok":true,"é":"%"}"#;
+        let form = from_json(body).unwrap();
+        assert_eq!(form, "name=a+b%26c&count=3&ok=true&%C3%A9=%25");
+        let json: Value = serde_json::from_str(&to_json(&form).unwrap()).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({"name": "a b&c", "count": "3", "ok": "true", "é": "%"})
+        );
+    }
+
+    #[test]
+    fn values_forms_cannot_hold_are_refused() {
+        assert_eq!(
+            from_json(r#"{"a":[1],"b":null,"c":"x","d":{}}"#),
+            Err("can't form-encode a, b, d".into())
+        );
+        assert_eq!(from_json("[1]"), Err("body is not a JSON object".into()));
+        assert!(to_json("a=%ZZ").is_err());
+        assert!(to_json("a=%FF").is_err());
+        assert_eq!(to_json("flag&&").unwrap(), "{\n  \"flag\": \"\"\n}");
+        assert_eq!(
+            to_json("a=1&b=2&a=3&c&c=4&a=5"),
+            Err("can't turn repeated a, c into JSON".into())
+        );
+    }
+}
@@ -0,0 +1,240 @@
+//! GraphQL schema introspection: what an endpoint offers, and request
+//! bodies scaffolded from it.
+
+use reqwest::blocking::Client;
+use serde_json::{json, Map, Value};
+
+use crate::{
+    config::Config,
+    http,
+    oauth::TokenSource,
+    request::{self, Request},
+};
+
+/// Asks for the operations and types, with type references nested deep
+/// enough for `[Item!]!`.
+const INTROSPECTION_QUERY: &str = "query IntrospectionQuery { __schema { \
+    queryType { name } mutationType { name } \
+    types { kind name fields { name args { name type { ...TypeRef } } type { ...TypeRef } } \
+    inputFields { name type { ...TypeRef } } enumValues { name } } } } \
+    fragment TypeRef on __Type { kind name ofType { kind name ofType { kind name \
+    ofType { kind name } } } }";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    Query,
+    Mutation,
+    Type,
+}
+
+/// A query, mutation or named type offered by the endpoint.
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub kind: Kind,
+    pub name: String,
+    /// Shown after the name: arguments and result for operations, kind
+    /// and members for types.
+    pub detail: String,
+    args: Vec<(String, String)>,
+    /// Fields picked for the selection set, empty for scalar results.
+    selection: Vec<String>,
+}
+
+/// Sends the introspection query to `base`'s URL with its headers, cookies
+/// and placeholders, and lists what the schema offers: queries, then
+/// mutations, then types.
+pub fn introspect(
+    client: &Client,
+    config: &Config,
+    base: &Request,
+    auth: Option<&TokenSource>,
+) -> Result<Vec<Entry>, String> {
+    let mut request = Request {
+        method: request::method_index("POST").unwrap_or_default(),
+        body: json!({ "query": INTROSPECTION_QUERY }).to_string(),
+        ..base.clone()
+    };
+    request.set_header("Content-Type", "application/json");
+    let response = http::execute(client, config, &request, auth)?;
+
+    let body: Value = serde_json::from_str(&response.body).map_err(|_| {
+        format!(
+            "Not a GraphQL endpoint: the introspection query got a non-JSON {} response",
+            response.status
+        )
+    })?;
+    let Some(schema) = body.pointer("/data/__schema") else {
+        let message = body
+            .pointer("/errors/0/message")
+            .and_then(Value::as_str)
+            .unwrap_or("no __schema in the response");
+        return Err(format!(
+            "Introspection failed ({}), it may be disabled on this endpoint: {}",
+            response.status, message
+        ));
+    };
+    Ok(entries(schema))
+}
+
+fn entries(schema: &Value) -> Vec<Entry> {
+    let types: Vec<&Value> = schema["types"].as_array().into_iter().flatten().collect();
+    let find = |name: &str| types.iter().copied().find(|t| t["name"] == name);
+    let root = |key: &str| schema[key]["name"].as_str().and_then(find);
+
+    let mut entries = Vec::new();
+    for (kind, key) in [(Kind::Query, "queryType"), (Kind::Mutation, "mutationType")] {
+        let Some(root) = root(key) else { continue };
+        for field in root["fields"].as_array().into_iter().flatten() {
+            let args: Vec<(String, String)> = field["args"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .map(|arg| (text(&arg["name"]), type_name(&arg["type"])))
+                .collect();
+            let result = &field["type"];
+            let selection = named_type(result)
+                .and_then(find)
+                .map(scalar_fields)
+                .unwrap_or_default();
+            let shown: Vec<String> = args.iter().map(|(n, t)| format!("{}: {}", n, t)).collect();
+            entries.push(Entry {
+                kind,
+                name: text(&field["name"]),
+                detail: if shown.is_empty() {
+                    format!(": {}", type_name(result))
+                } else {
+                    format!("({}): {}", shown.join(", "), type_name(result))
+                },
+                args,
+                selection,
+            });
+        }
+    }
+
+    for t in &types {
+        let name = text(&t["name"]);
+        if name.starts_with("__") {
+            continue;
+        }
+        let members: Vec<String> = ["fields", "inputFields", "enumValues"]
+            .iter()
+            .flat_map(|key| t[*key].as_array().into_iter().flatten())
+            .map(|member| text(&member["name"]))
+            .collect();
+        entries.push(Entry {
+            kind: Kind::Type,
+            name,
+            detail: if members.is_empty() {
+                format!(" {}", text(&t["kind"]).to_lowercase())
+            } else {
+                format!(
+                    " {} {{ {} }}",
+                    text(&t["kind"]).to_lowercase(),
+                    members.join(", ")
+                )
+            },
+            args: Vec::new(),
+            selection: Vec::new(),
+        });
+    }
+    entries
+}
+
+/// Fields of object type `t` that can be selected without a sub-selection
+/// or arguments, `__typename` if there are none.
+fn scalar_fields(t: &Value) -> Vec<String> {
+    if !matches!(t["kind"].as_str(), Some("OBJECT" | "INTERFACE" | "UNION")) {
+        return Vec::new();
+    }
+    let fields: Vec<String> = t["fields"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|field| field["args"].as_array().is_none_or(Vec::is_empty))
+        .filter(|field| matches!(leaf_kind(&field["type"]), Some("SCALAR" | "ENUM")))
+        .map(|field| text(&field["name"]))
+        .collect();
+    if fields.is_empty() {
+        vec!["__typename".into()]
+    } else {
+        fields
+    }
+}
+
+/// The body of a request running `entry`, its arguments as variables left
+/// `null` to fill in. `None` for types.
+pub fn scaffold(entry: &Entry) -> Option<String> {
+    let keyword = match entry.kind {
+        Kind::Query => "query",
+        Kind::Mutation => "mutation",
+        Kind::Type => return None,
+    };
+    let mut query = format!("{} {}", keyword, operation_name(&entry.name));
+    let mut variables = Map::new();
+    if !entry.args.is_empty() {
+        let declared: Vec<String> = entry
+            .args
+            .iter()
+            .map(|(name, t)| format!("${}: {}", name, t))
+            .collect();
+        let passed: Vec<String> = entry
+            .args
+            .iter()
+            .map(|(name, _)| format!("{}: ${}", name, name))
+            .collect();
+        query.push_str(&format!(
+            "({}) {{ {}({})",
+            declared.join(", "),
+            entry.name,
+            passed.join(", ")
+        ));
+        for (name, _) in &entry.args {
+            variables.insert(name.clone(), Value::Null);
+        }
+    } else {
+        query.push_str(&format!(" {{ {}", entry.name));
+    }
+    if !entry.selection.is_empty() {
+        query.push_str(&format!(" {{ {} }}", entry.selection.join(" ")));
+    }
+    query.push_str(" }");
+
+    serde_json::to_string_pretty(&json!({ "query": query, "variables": variables })).ok()
+}
+
+/// `users` as an operation name: `Users`.
+fn operation_name(field: &str) -> String {
+    let mut chars = field.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// A type reference written the GraphQL way, e.g. `[Item!]!`.
+fn type_name(t: &Value) -> String {
+    match t["kind"].as_str() {
+        Some("NON_NULL") => format!("{}!", type_name(&t["ofType"])),
+        Some("LIST") => format!("[{}]", type_name(&t["ofType"])),
+        _ => text(&t["name"]),
+    }
+}
+
+/// The named type inside any list and non-null wrappers.
+fn named_type(t: &Value) -> Option<&str> {
+    match t["kind"].as_str() {
+        Some("NON_NULL" | "LIST") => named_type(&t["ofType"]),
+        _ => t["name"].as_str(),
+    }
+}
+
+fn leaf_kind(t: &Value) -> Option<&str> {
+    match t["kind"].as_str() {
+        Some("NON_NULL" | "LIST") => leaf_kind(&t["ofType"]),
+        kind => kind,
+    }
+}
+
+fn text(value: &Value) -> String {
+    value.as_str().unwrap_or_default().to_string()
+}
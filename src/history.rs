@@ -0,0 +1,46 @@
+//! Requests sent so far, newest first, stored as `history.json` in the
+//! config directory.
+//!
+//! Like recordings, entries hold the request as typed, with placeholders
+//! unresolved.
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+use crate::{request::Request, store};
+
+const FILE: &str = "history.json";
+
+/// Entries kept before the oldest are dropped.
+const MAX_ENTRIES: usize = 200;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    pub request: Request,
+    pub sent_at: DateTime<Local>,
+    /// Status code of the response, `None` if sending failed.
+    pub status: Option<u16>,
+}
+
+#[derive(Debug, Default)]
+pub struct History {
+    pub entries: Vec<Entry>,
+}
+
+impl History {
+    /// Loads the history file; a missing file means an empty history.
+    pub fn load() -> Result<Self, String> {
+        store::load(FILE).map(|entries| Self { entries })
+    }
+
+    /// Adds `entry` at the top and writes the file.
+    pub fn push(&mut self, entry: Entry) -> Result<(), String> {
+        self.entries.insert(0, entry);
+        self.entries.truncate(MAX_ENTRIES);
+        self.save()
+    }
+
+    fn save(&self) -> Result<(), String> {
+        store::save(FILE, &self.entries)
+    }
+}
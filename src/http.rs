@@ -0,0 +1,579 @@
+//! Building the HTTP client and sending requests with it.
+
+use std::{
+    error::Error,
+    fmt, fs,
+    io::{self, Read},
+    iter,
+    net::{SocketAddr, ToSocketAddrs},
+    path::Path,
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use chrono::Local;
+use flate2::read::GzDecoder;
+use hyper_util::client::legacy::connect::HttpInfo;
+use reqwest::{
+    blocking::{Client, ClientBuilder},
+    dns::{Addrs, Name, Resolve, Resolving},
+    header::{HeaderName, HeaderValue, CONTENT_ENCODING, COOKIE},
+    tls::TlsInfo,
+    Certificate,
+};
+
+use crate::{
+    config::Config,
+    jars::Jars,
+    oauth::TokenSource,
+    query, redirect,
+    request::Request,
+    response::{Connection, Response},
+    stream::{Chunk, Utf8Decoder},
+    template,
+};
+
+/// Size of each read from the response body.
+const READ_SIZE: usize = 8 * 1024;
+
+/// First bytes of any gzip stream.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Progress of a request sent with [`spawn`].
+#[derive(Debug)]
+pub enum Event {
+    /// Part of the body arrived.
+    Chunk(Chunk),
+    /// The request is sent again; chunks so far were for the abandoned
+    /// response.
+    Restart,
+    /// The request finished; the response holds the complete body. The
+    /// error is the text to show in the response pane instead.
+    Done(Result<Response, String>),
+}
+
+/// Builds the client shared by every request until the config changes.
+/// With a `jar`, cookies set by responses are stored and sent back.
+pub fn client(config: &Config, jar: Option<Arc<Jars>>) -> Result<Client, String> {
+    builder(config, jar)?.build().map_err(|err| err.to_string())
+}
+
+/// A client for `request`, which has an SNI override: it connects to the
+/// URL's host, while [`send`] puts the SNI name in the URL to have it used
+/// in the TLS handshake and certificate check.
+pub fn sni_client(
+    config: &Config,
+    jar: Option<Arc<Jars>>,
+    request: &Request,
+) -> Result<Client, String> {
+    sni_builder(config, jar, request)?
+        .build()
+        .map_err(|err| err.to_string())
+}
+
+/// A client of its own for `request`, over a new connection whose bytes
+/// are logged for [`trace`](crate::trace) to capture.
+pub fn verbose_client(
+    config: &Config,
+    jar: Option<Arc<Jars>>,
+    request: &Request,
+) -> Result<Client, String> {
+    let builder = if request.sni.is_empty() {
+        builder(config, jar)?
+    } else {
+        sni_builder(config, jar, request)?
+    };
+    builder
+        .connection_verbose(true)
+        .tls_info(true)
+        .build()
+        .map_err(|err| err.to_string())
+}
+
+fn sni_builder(
+    config: &Config,
+    jar: Option<Arc<Jars>>,
+    request: &Request,
+) -> Result<ClientBuilder, String> {
+    let url = reqwest::Url::parse(&query::encode(&template::resolve(&request.url)?))
+        .map_err(|err| format!("Request not sent: invalid URL: {}", err))?;
+    let resolver = SniResolver {
+        sni: request.sni.clone(),
+        host: url.host_str().unwrap_or_default().to_string(),
+    };
+    Ok(builder(config, jar)?.dns_resolver(Arc::new(resolver)))
+}
+
+/// Looks the SNI name up as the URL's host, and other names, like those
+/// redirects lead to, as themselves. Lookups happen when a connection is
+/// opened, on a blocking task of the client's runtime.
+struct SniResolver {
+    sni: String,
+    host: String,
+}
+
+impl Resolve for SniResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = if name.as_str().eq_ignore_ascii_case(&self.sni) {
+            self.host.trim_matches(['[', ']']).to_string()
+        } else {
+            name.as_str().to_string()
+        };
+        Box::pin(async move {
+            let addrs = tokio::task::spawn_blocking(move || {
+                // Port 0 is replaced with the URL's port when connecting
+                (host.as_str(), 0)
+                    .to_socket_addrs()
+                    .map(Iterator::collect::<Vec<SocketAddr>>)
+                    .map_err(|_| Unresolved(host))
+            })
+            .await??;
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// The URL's host of a request with an SNI override didn't resolve.
+#[derive(Debug)]
+struct Unresolved(String);
+
+impl fmt::Display for Unresolved {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Request not sent: can't resolve {}", self.0)
+    }
+}
+
+impl Error for Unresolved {}
+
+fn builder(config: &Config, jar: Option<Arc<Jars>>) -> Result<ClientBuilder, String> {
+    let mut builder = Client::builder()
+        .redirect(redirect::policy())
+        .timeout(Duration::from_secs(config.timeout_secs));
+    if let Some(jar) = jar {
+        builder = builder.cookie_provider(jar);
+    }
+    for certificate in root_certificates(config)? {
+        builder = builder.add_root_certificate(certificate);
+    }
+    Ok(builder)
+}
+
+/// The certificates in the configured CA bundle, if there is one.
+fn root_certificates(config: &Config) -> Result<Vec<Certificate>, String> {
+    match &config.ca_bundle {
+        Some(path) => ca_certificates(path),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Reads the PEM certificates in `path`, which must hold at least one.
+pub fn ca_certificates(path: &Path) -> Result<Vec<Certificate>, String> {
+    let error = |message: String| format!("CA bundle {}: {}", path.display(), message);
+    let pem = fs::read(path).map_err(|err| error(err.to_string()))?;
+    let certificates = Certificate::from_pem_bundle(&pem).map_err(|err| error(err.to_string()))?;
+    if certificates.is_empty() {
+        return Err(error("no PEM certificates found".into()));
+    }
+    Ok(certificates)
+}
+
+/// Sends `request` on a background thread, reporting body chunks as they
+/// arrive and then the complete response. `auth` provides the token for
+/// `{{oauth2.token}}`.
+pub fn spawn(
+    client: &Client,
+    config: &Config,
+    request: &Request,
+    auth: Option<Arc<TokenSource>>,
+) -> Receiver<Event> {
+    let (tx, rx) = mpsc::channel();
+    let client = client.clone();
+    let expect_continue = config.expect_continue;
+    let request = request.clone();
+    thread::spawn(move || {
+        let result = send_with_token(&client, expect_continue, &request, auth.as_deref(), &tx);
+        // The receiver is gone if the app stopped waiting, nothing to do then
+        let _ = tx.send(Event::Done(result));
+    });
+    rx
+}
+
+/// Sends `request` and waits for the complete response. `auth` provides
+/// the token for `{{oauth2.token}}`.
+pub fn execute(
+    client: &Client,
+    config: &Config,
+    request: &Request,
+    auth: Option<&TokenSource>,
+) -> Result<Response, String> {
+    // Nobody listens for the chunks
+    let (tx, _) = mpsc::channel();
+    send_with_token(client, config.expect_continue, request, auth, &tx)
+}
+
+/// Fills in the OAuth2 token if `request` uses it and sends it, retrying
+/// once with a fresh token on a 401.
+fn send_with_token(
+    client: &Client,
+    expect_continue: bool,
+    request: &Request,
+    auth: Option<&TokenSource>,
+    events: &Sender<Event>,
+) -> Result<Response, String> {
+    if !template::uses(request, template::OAUTH2_TOKEN) {
+        return send(client, expect_continue, request, events);
+    }
+    let Some(auth) = auth else {
+        return Err("Request not sent: {{oauth2.token}} needs an [oauth2] config section".into());
+    };
+
+    let token = auth
+        .token(client)
+        .map_err(|err| format!("Request not sent: OAuth2 token: {}", err))?;
+    let response = send(
+        client,
+        expect_continue,
+        &template::fill(request, template::OAUTH2_TOKEN, &token),
+        events,
+    )?;
+    if response.status != 401 || !auth.retry_on_401() {
+        return Ok(response);
+    }
+
+    let token = auth
+        .refresh(client)
+        .map_err(|err| format!("Got 401 and refreshing the OAuth2 token failed: {}", err))?;
+    let _ = events.send(Event::Restart);
+    let mut retried = send(
+        client,
+        expect_continue,
+        &template::fill(request, template::OAUTH2_TOKEN, &token),
+        events,
+    )?;
+    retried.auth_retried = true;
+    Ok(retried)
+}
+
+/// Resolves placeholders in `request` and sends it, with
+/// `Expect: 100-continue` if `expect_continue` and it has a body.
+fn send(
+    client: &Client,
+    expect_continue: bool,
+    request: &Request,
+    events: &Sender<Event>,
+) -> Result<Response, String> {
+    let mut resolved =
+        template::resolve_request(request).map_err(|err| format!("Request not sent: {}", err))?;
+    if wants_continue(expect_continue, &resolved) {
+        resolved.set_header("Expect", "100-continue");
+    }
+    let mut url = query::encode(&resolved.url);
+    if !resolved.sni.is_empty() {
+        let mut parsed = reqwest::Url::parse(&url)
+            .map_err(|err| format!("Request not sent: invalid URL: {}", err))?;
+        parsed
+            .set_host(Some(&resolved.sni))
+            .map_err(|err| format!("Request not sent: SNI override: {}", err))?;
+        url = parsed.to_string();
+    }
+    if !resolved.host.is_empty() {
+        let host = resolved.host.clone();
+        resolved.set_header("Host", &host);
+    }
+    let body = resolved
+        .body_bytes()
+        .map_err(|err| format!("Request not sent: {}", err))?;
+    let body = resolved.sends_body().then_some(body);
+    make_request(
+        client,
+        resolved.method_name(),
+        &url,
+        &resolved.headers,
+        &resolved.cookies,
+        body,
+        events,
+    )
+}
+
+/// Whether `request` goes out with `Expect: 100-continue`: only when the
+/// config asks for it, the request has a body and no `Expect` of its own.
+///
+/// The body follows the headers without waiting for the server's interim
+/// `100 Continue`, which hyper reads and discards; a server that rejects
+/// the upload still answers with its final status. Verbose sends show the
+/// interim response in their trace.
+pub fn expects_continue(config: &Config, request: &Request) -> bool {
+    wants_continue(config.expect_continue, request)
+}
+
+fn wants_continue(expect_continue: bool, request: &Request) -> bool {
+    expect_continue
+        && request.sends_body()
+        && !request.body.is_empty()
+        && request.header("expect").is_none()
+}
+
+/// Why a header row `name: value` would fail to send, if it would. Rows
+/// with placeholders are only checked once filled in, at send time.
+pub fn header_error(name: &str, value: &str) -> Option<String> {
+    if name.contains("{{") || value.contains("{{") {
+        return None;
+    }
+    if HeaderName::from_bytes(name.as_bytes()).is_err() {
+        let token = |c: char| c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c);
+        return Some(match name.chars().find(|&c| !token(c)) {
+            Some(c) => format!("{:?} is not allowed in a header name", c),
+            None => "Header name is empty".into(),
+        });
+    }
+    if HeaderValue::from_str(value).is_err() {
+        let bad = value.chars().find(|&c| c.is_ascii_control() && c != '\t');
+        return Some(match bad {
+            Some(c) => format!("{:?} is not allowed in a header value", c),
+            None => "Invalid header value".into(),
+        });
+    }
+    None
+}
+
+/// Why `host` can't be sent as the `Host` header override, if it can't.
+pub fn host_override_error(host: &str) -> Option<String> {
+    let valid = host.contains("{{")
+        || (!host.contains(['/', '?', '#', '@', ' '])
+            && reqwest::Url::parse(&format!("http://{}/", host))
+                .is_ok_and(|url| url.host_str().is_some_and(|h| !h.is_empty())));
+    (!valid).then(|| {
+        format!(
+            "{:?} is not a host with an optional port, e.g. api.example.com:8443",
+            host
+        )
+    })
+}
+
+/// Why `name` can't be used as the TLS server name, if it can't: it must be
+/// a DNS name, without a port.
+pub fn sni_error(name: &str) -> Option<String> {
+    let valid = name.contains("{{")
+        || (!name.contains(['/', '?', '#', '@', ':', ' '])
+            && reqwest::Url::parse(&format!("https://{}/", name))
+                .is_ok_and(|url| url.domain().is_some()));
+    (!valid).then(|| format!("{:?} is not a DNS name, e.g. api.example.com", name))
+}
+
+/// A header the request goes out with, and whether reqwest or hyper add it
+/// rather than the user.
+pub type WireHeader = (String, String, bool);
+
+/// The headers `request` will be sent with: the explicit ones, then those
+/// added on the way out. `jar_cookies` is what the cookie jar holds for the
+/// URL. Placeholders are left as typed.
+pub fn effective_headers(
+    config: &Config,
+    request: &Request,
+    jar_cookies: Option<&str>,
+) -> Vec<WireHeader> {
+    let mut headers: Vec<WireHeader> = request
+        .headers
+        .iter()
+        .map(|(name, value)| (name.clone(), value.clone(), false))
+        .collect();
+    if !request.cookies.is_empty() {
+        headers.push(("Cookie".into(), request.cookies.clone(), false));
+    }
+    if !request.host.is_empty() {
+        headers.retain(|(name, _, _)| !name.eq_ignore_ascii_case("host"));
+        headers.push(("Host".into(), request.host.clone(), false));
+    }
+
+    let mut auto = |name: &str, value: String| {
+        let explicit = headers.iter().any(|(n, _, _)| n.eq_ignore_ascii_case(name));
+        if !explicit {
+            headers.push((name.to_ascii_lowercase(), value, true));
+        }
+    };
+    if let Ok(url) = reqwest::Url::parse(&query::encode(&request.url)) {
+        let host = match request.sni.as_str() {
+            "" => url.host_str().unwrap_or_default(),
+            sni => sni,
+        };
+        match url.port() {
+            Some(port) => auto("host", format!("{}:{}", host, port)),
+            None => auto("host", host.to_string()),
+        }
+    }
+    auto("accept", "*/*".into());
+    if expects_continue(config, request) {
+        auto("expect", "100-continue".into());
+    }
+    if request.sends_body() {
+        let length = request
+            .body_bytes()
+            .map_or(request.body.len(), |body| body.len());
+        auto("content-length", length.to_string());
+    }
+    if let (true, Some(cookies)) = (request.cookies.is_empty(), jar_cookies) {
+        auto("cookie", cookies.to_string());
+    }
+    headers
+}
+
+/// Handles making an HTTP request based on user selection
+fn make_request(
+    client: &Client,
+    method: &str,
+    url: &str,
+    headers: &[(String, String)],
+    cookies: &str,
+    body: Option<Vec<u8>>,
+    events: &Sender<Event>,
+) -> Result<Response, String> {
+    let mut request = match method {
+        "GET" => client.get(url),
+        "POST" => client.post(url),
+        "PUT" => client.put(url),
+        "DELETE" => client.delete(url),
+        "PATCH" => client.patch(url),
+        _ => return Err("Invalid Method".to_string()),
+    };
+
+    for (key, value) in headers {
+        request = request.header(key, value);
+    }
+
+    if !cookies.is_empty() {
+        request = request.header(COOKIE, cookies);
+    }
+
+    if let Some(body) = body {
+        request = request.body(body);
+    }
+
+    let started = Instant::now();
+    let res = request.send().map_err(|err| {
+        let unresolved =
+            iter::successors(Some(&err as &(dyn Error + 'static)), |&err| err.source())
+                .find_map(|err| err.downcast_ref::<Unresolved>());
+        match (redirect::RedirectError::find(&err), unresolved) {
+            (Some(redirect), _) => redirect.to_string(),
+            (None, Some(unresolved)) => unresolved.to_string(),
+            (None, None) => format!("Failed to make request: {}", err),
+        }
+    })?;
+
+    let status = res.status().as_u16();
+    let url = res.url().to_string();
+    let connection = res.extensions().get::<HttpInfo>().map(|info| Connection {
+        local: info.local_addr(),
+        remote: info.remote_addr(),
+    });
+    let certificate_size = res
+        .extensions()
+        .get::<TlsInfo>()
+        .and_then(TlsInfo::peer_certificate)
+        .map(<[u8]>::len);
+    let headers = res
+        .headers()
+        .iter()
+        .map(|(k, v)| {
+            (
+                k.to_string(),
+                String::from_utf8_lossy(v.as_bytes()).into_owned(),
+            )
+        })
+        .collect();
+    let labelled_gzip = res
+        .headers()
+        .get(CONTENT_ENCODING)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.to_ascii_lowercase().contains("gzip"));
+    let (bytes, encoding_note) = read_body(res, events, labelled_gzip)?;
+    let (body, raw) = Response::body_from(bytes);
+
+    Ok(Response {
+        status,
+        headers,
+        body,
+        raw,
+        elapsed: started.elapsed(),
+        url,
+        replayed: false,
+        auth_retried: false,
+        connection,
+        encoding_note,
+        certificate_size,
+    })
+}
+
+/// Reads the body to the end, forwarding it as [`Chunk`]s, along with a
+/// note when it doesn't match its `Content-Encoding`. Whether the body is
+/// gzipped is told by its first bytes, as some servers label gzip wrongly;
+/// one that doesn't decompress is shown raw.
+fn read_body(
+    mut res: impl Read,
+    events: &Sender<Event>,
+    labelled_gzip: bool,
+) -> Result<(Vec<u8>, Option<String>), String> {
+    let mut head = Vec::new();
+    (&mut res)
+        .take(GZIP_MAGIC.len() as u64)
+        .read_to_end(&mut head)
+        .map_err(read_error)?;
+    let gzipped = head == GZIP_MAGIC;
+    let mut res = io::Cursor::new(head).chain(res);
+    if !gzipped {
+        let note = labelled_gzip.then(|| "Content-Encoding: gzip, but not gzipped".to_string());
+        return Ok((read_text(res, events)?, note));
+    }
+
+    // Compressed, so it can only be shown once complete
+    let mut raw = Vec::new();
+    res.read_to_end(&mut raw).map_err(read_error)?;
+    let mut inflated = Vec::new();
+    let (bytes, note) = match GzDecoder::new(raw.as_slice()).read_to_end(&mut inflated) {
+        Ok(_) if labelled_gzip => (inflated, None),
+        Ok(_) => (
+            inflated,
+            Some("gzipped without Content-Encoding: gzip, decompressed".to_string()),
+        ),
+        Err(err) => (
+            raw,
+            Some(format!("gzip didn't decompress ({}), shown raw", err)),
+        ),
+    };
+    Ok((read_text(bytes.as_slice(), events)?, note))
+}
+
+fn read_error(err: io::Error) -> String {
+    format!("Failed while reading response: {}", err)
+}
+
+/// Reads the body to the end, forwarding each read as a [`Chunk`] of text.
+fn read_text(mut res: impl Read, events: &Sender<Event>) -> Result<Vec<u8>, String> {
+    let mut body = Vec::new();
+    let mut decoder = Utf8Decoder::default();
+    let mut buf = vec![0; READ_SIZE];
+
+    loop {
+        let read = res.read(&mut buf).map_err(read_error)?;
+        let text = if read == 0 {
+            decoder.finish()
+        } else {
+            decoder.push(&buf[..read])
+        };
+        body.extend_from_slice(&buf[..read]);
+        if !text.is_empty() {
+            let chunk = Chunk {
+                at: Local::now(),
+                text,
+            };
+            let _ = events.send(Event::Chunk(chunk));
+        }
+        if read == 0 {
+            return Ok(body);
+        }
+    }
+}
@@ -0,0 +1,232 @@
+//! Export and import of requests in the `.http`/`.rest` file format used by
+//! the VS Code REST Client and JetBrains HTTP Client.
+//!
+//! Only the first request of a file is imported. Comments (`#`, `//`),
+//! variable definitions (`@name = value`) and `###` separators before it are
+//! skipped; the request line may omit the method (defaulting to `GET`) and
+//! the HTTP version. A request's expected response is kept in the comments
+//! following a `# @expected` line, a `# @base64` line marks a body sent
+//! decoded from base64, and `# @cookies`, `# @host` and `# @sni` lines hold
+//! the request's overrides. The body is kept as it is, up to the line
+//! break ending it.
+
+use crate::request::{self, Request};
+
+const EXPECTED: &str = "# @expected";
+const BASE64: &str = "# @base64";
+const COOKIES: &str = "# @cookies ";
+const HOST: &str = "# @host ";
+const SNI: &str = "# @sni ";
+
+/// Renders `request` as a `.http` document: request line, headers, a blank
+/// line and the body, after the expected response if there is one.
+pub fn export(request: &Request) -> String {
+    let mut out = String::new();
+    // Ahead of the expected response, whose comments run to the request line
+    if request.body_base64 {
+        out.push_str(BASE64);
+        out.push('\n');
+    }
+    for (prefix, value) in [
+        (COOKIES, &request.cookies),
+        (HOST, &request.host),
+        (SNI, &request.sni),
+    ] {
+        if !value.is_empty() {
+            out.push_str(&format!("{}{}\n", prefix, value));
+        }
+    }
+    if !request.expected.is_empty() {
+        out.push_str(EXPECTED);
+        out.push('\n');
+        for line in request.expected.lines() {
+            if line.is_empty() {
+                out.push_str("#\n");
+            } else {
+                out.push_str(&format!("# {}\n", line));
+            }
+        }
+    }
+    out.push_str(&format!("{} {}\n", request.method_name(), request.url));
+    for (name, value) in &request.headers {
+        out.push_str(&format!("{}: {}\n", name, value));
+    }
+    if !request.body.is_empty() {
+        out.push('\n');
+        out.push_str(&request.body);
+        // Ends the body's last line; import takes it off again
+        out.push('\n');
+    }
+    out
+}
+
+fn is_comment(line: &str) -> bool {
+    let line = line.trim_start();
+    (line.starts_with('#') && !line.starts_with("###")) || line.starts_with("//")
+}
+
+fn is_separator(line: &str) -> bool {
+    line.trim_start().starts_with("###")
+}
+
+/// Parses the first request found in a `.http` document.
+pub fn import(text: &str) -> Result<Request, String> {
+    let mut lines = text.lines().enumerate().peekable();
+    // Byte offset of each line, for taking the body as it is
+    let starts: Vec<usize> = text
+        .split_inclusive('\n')
+        .scan(0, |at, line| {
+            let start = *at;
+            *at += line.len();
+            Some(start)
+        })
+        .collect();
+
+    // Skip everything up to the request line
+    let mut expected = Vec::new();
+    let mut request = Request::default();
+    let (line_no, request_line) = loop {
+        let Some((no, line)) = lines.next() else {
+            return Err("no request found".into());
+        };
+        let trimmed = line.trim();
+        if trimmed == EXPECTED {
+            while let Some((_, line)) =
+                lines.next_if(|(_, line)| line.starts_with('#') && !is_separator(line))
+            {
+                expected.push(line[1..].strip_prefix(' ').unwrap_or(&line[1..]));
+            }
+            continue;
+        }
+        if trimmed == BASE64 {
+            request.body_base64 = true;
+            continue;
+        }
+        let line = line.trim_start();
+        if let Some(cookies) = line.strip_prefix(COOKIES) {
+            request.cookies = cookies.trim().to_string();
+            continue;
+        }
+        if let Some(host) = line.strip_prefix(HOST) {
+            request.host = host.trim().to_string();
+            continue;
+        }
+        if let Some(sni) = line.strip_prefix(SNI) {
+            request.sni = sni.trim().to_string();
+            continue;
+        }
+        if trimmed.is_empty()
+            || is_comment(trimmed)
+            || is_separator(trimmed)
+            || trimmed.starts_with('@')
+        {
+            continue;
+        }
+        break (no + 1, trimmed);
+    };
+
+    let mut parts = request_line.split_whitespace();
+    let first = parts.next().unwrap_or_default();
+    let (method, mut url) = match request::method_index(first) {
+        Some(method) => match parts.next() {
+            Some(url) => (method, url.to_string()),
+            None => return Err(format!("line {}: missing URL after {}", line_no, first)),
+        },
+        None if first.contains("://") || first.starts_with('/') => (0, first.to_string()),
+        None => {
+            return Err(format!(
+                "line {}: unsupported method '{}' (expected one of {})",
+                line_no,
+                first,
+                request::METHODS.join(", ")
+            ))
+        }
+    };
+
+    // Query parameters may continue on the following lines
+    while let Some((_, line)) = lines.peek() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('?') || trimmed.starts_with('&') {
+            url.push_str(trimmed);
+            lines.next();
+        } else {
+            break;
+        }
+    }
+
+    request.method = method;
+    request.url = url;
+    request.expected = expected.join("\n");
+    for (no, line) in lines.by_ref() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            break;
+        }
+        if is_separator(trimmed) {
+            return Ok(request);
+        }
+        if is_comment(trimmed) {
+            continue;
+        }
+        match trimmed.split_once(':') {
+            Some((name, value)) => request
+                .headers
+                .push((name.trim().to_string(), value.trim().to_string())),
+            None => return Err(format!("line {}: expected 'Name: value' header", no + 1)),
+        }
+    }
+
+    // Up to the next request, without the line break ending the body
+    let start = lines.peek().map_or(text.len(), |(no, _)| starts[*no]);
+    let end = lines
+        .find(|(_, line)| is_separator(line))
+        .map_or(text.len(), |(no, _)| starts[no]);
+    let body = &text[start..end];
+    let body = match body.strip_suffix('\n') {
+        Some(body) => body.strip_suffix('\r').unwrap_or(body),
+        None => body,
+    };
+    request.body = body.to_string();
+    Ok(request)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests_survive_export_and_import() {
+        let request = Request {
+            method: 1,
+            url: "https://api.example.com/items?page=2".into(),
+            headers: vec![("Content-Type".into(), "application/json".into())],
+            body: "{\"a\": 1}\n\n".into(),
+            cookies: "sid=abc; theme=dark".into(),
+            host: "internal.example.com".into(),
+            sni: "edge.example.com".into(),
+            expected: "{\"ok\": true,  \n\n  \"n\": 1\t}".into(),
+            ..Request::default()
+        };
+        let exported = export(&request);
+        assert!(!exported.contains("Cookie:"));
+        assert_eq!(import(&exported).unwrap(), request);
+
+        let bare = Request {
+            url: "http://localhost/".into(),
+            body: "no newline".into(),
+            ..Request::default()
+        };
+        assert_eq!(import(&export(&bare)).unwrap(), bare);
+    }
+
+    #[test]
+    fn import_reads_the_first_request_of_a_file() {
+        let text = "@host = x\n// note\n### first\nPOST http://h/a\n  ?b=1\n  &c=2\nAccept: */*\n\r\nline 1\r\nline 2\r\n###\nGET http://h/other\n";
+        let request = import(text).unwrap();
+        assert_eq!(request.method_name(), "POST");
+        assert_eq!(request.url, "http://h/a?b=1&c=2");
+        assert_eq!(request.headers, [("Accept".to_string(), "*/*".to_string())]);
+        assert_eq!(request.body, "line 1\r\nline 2");
+        assert!(import("FETCH http://h/").is_err());
+    }
+}
@@ -0,0 +1,168 @@
+//! Named cookie jars, for keeping the sessions of several accounts on the
+//! same site apart. One jar is active at a time; responses store their
+//! cookies in it and requests send its cookies. Optionally stored as
+//! `cookie-jars.json` in the config directory.
+
+use std::sync::{Mutex, MutexGuard};
+
+use cookie_store::{Cookie, CookieStore, RawCookie};
+use reqwest::{header::HeaderValue, Url};
+use serde::{Deserialize, Serialize};
+
+use crate::store;
+
+const FILE: &str = "cookie-jars.json";
+
+/// The jar active until another one is picked.
+pub const DEFAULT: &str = "default";
+
+pub struct Jars {
+    inner: Mutex<Inner>,
+}
+
+struct Inner {
+    active: usize,
+    jars: Vec<(String, CookieStore)>, // In the order they were created
+}
+
+/// How the jars are stored: their unexpired cookies, session ones too.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct Saved {
+    active: String,
+    jars: Vec<SavedJar>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedJar {
+    name: String,
+    cookies: Vec<Cookie<'static>>,
+}
+
+impl Default for Jars {
+    fn default() -> Self {
+        Jars {
+            inner: Mutex::new(Inner {
+                active: 0,
+                jars: vec![(DEFAULT.into(), CookieStore::default())],
+            }),
+        }
+    }
+}
+
+impl Jars {
+    fn inner(&self) -> MutexGuard<'_, Inner> {
+        self.inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Name of the active jar.
+    pub fn active(&self) -> String {
+        let inner = self.inner();
+        inner.jars[inner.active].0.clone()
+    }
+
+    /// Number of cookies in the active jar.
+    pub fn count(&self) -> usize {
+        let inner = self.inner();
+        inner.jars[inner.active].1.iter_unexpired().count()
+    }
+
+    /// Every jar with the number of cookies it holds, and whether it's the
+    /// active one.
+    pub fn list(&self) -> Vec<(String, usize, bool)> {
+        let inner = self.inner();
+        inner
+            .jars
+            .iter()
+            .enumerate()
+            .map(|(i, (name, jar))| {
+                (
+                    name.clone(),
+                    jar.iter_unexpired().count(),
+                    i == inner.active,
+                )
+            })
+            .collect()
+    }
+
+    /// Makes the jar called `name` active, creating it empty if there's
+    /// none yet. Tells whether it was created.
+    pub fn switch(&self, name: &str) -> bool {
+        let mut inner = self.inner();
+        if let Some(i) = inner.jars.iter().position(|(jar, _)| jar == name) {
+            inner.active = i;
+            return false;
+        }
+        inner.jars.push((name.into(), CookieStore::default()));
+        inner.active = inner.jars.len() - 1;
+        true
+    }
+
+    /// Replaces the jars with the stored ones; a missing file leaves them
+    /// as they are.
+    pub fn load(&self) -> Result<(), String> {
+        let saved: Saved = store::load(FILE)?;
+        if saved.jars.is_empty() {
+            return Ok(());
+        }
+        let mut jars = Vec::new();
+        for jar in saved.jars {
+            let cookies =
+                CookieStore::from_cookies(jar.cookies.into_iter().map(Ok::<_, ()>), false)
+                    .unwrap_or_default();
+            jars.push((jar.name, cookies));
+        }
+        let mut inner = self.inner();
+        inner.active = jars
+            .iter()
+            .position(|(name, _)| *name == saved.active)
+            .unwrap_or_default();
+        inner.jars = jars;
+        Ok(())
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let inner = self.inner();
+        let saved = Saved {
+            active: inner.jars[inner.active].0.clone(),
+            jars: inner
+                .jars
+                .iter()
+                .map(|(name, jar)| SavedJar {
+                    name: name.clone(),
+                    cookies: jar.iter_unexpired().cloned().collect(),
+                })
+                .collect(),
+        };
+        store::save(FILE, &saved)
+    }
+}
+
+/// Cookies go to and come from the jar active at the time, so switching
+/// jars while a request is in flight stores its cookies in the new one.
+impl reqwest::cookie::CookieStore for Jars {
+    fn set_cookies(&self, headers: &mut dyn Iterator<Item = &HeaderValue>, url: &Url) {
+        let cookies = headers
+            .filter_map(|header| header.to_str().ok())
+            .filter_map(|header| RawCookie::parse(header.to_string()).ok());
+        let mut inner = self.inner();
+        let active = inner.active;
+        inner.jars[active].1.store_response_cookies(cookies, url);
+    }
+
+    fn cookies(&self, url: &Url) -> Option<HeaderValue> {
+        let inner = self.inner();
+        let header = inner.jars[inner.active]
+            .1
+            .get_request_values(url)
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect::<Vec<_>>()
+            .join("; ");
+        if header.is_empty() {
+            return None;
+        }
+        HeaderValue::from_str(&header).ok()
+    }
+}
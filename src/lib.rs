@@ -0,0 +1,52 @@
+//! The request model and sending logic behind the LazyCurl TUI, usable on
+//! its own.
+//!
+//! A [`request::Request`] holds a request as typed, placeholders and all.
+//! [`http::execute`] resolves the placeholders ([`template`]) and sends it
+//! with a client from [`http::client`]; [`httpfile`] reads and writes the
+//! `.http` file format.
+//!
+//! ```no_run
+//! use lazycurl::{config::Config, http, request::{self, Request}};
+//!
+//! let request = Request {
+//!     method: request::method_index("POST").unwrap(),
+//!     url: "https://example.com/items".into(),
+//!     headers: vec![("Authorization".into(), "Bearer {{secret.api}}".into())],
+//!     body: r#"{"name": "widget"}"#.into(),
+//!     ..Request::default()
+//! };
+//! let config = Config::default();
+//! let client = http::client(&config, None)?;
+//! let response = http::execute(&client, &config, &request, None)?;
+//! println!("{} {}", response.status, response.body);
+//! # Ok::<(), String>(())
+//! ```
+
+pub mod baseline;
+pub mod batch;
+pub mod binary;
+pub mod compare;
+pub mod config;
+pub mod dotenv;
+pub mod form;
+pub mod graphql;
+pub mod history;
+pub mod http;
+pub mod httpfile;
+pub mod jars;
+pub mod oauth;
+pub mod preferences;
+pub mod query;
+pub mod recording;
+mod redirect;
+pub mod request;
+pub mod response;
+pub mod save;
+pub mod secrets;
+pub mod stats;
+pub mod store;
+pub mod stream;
+pub mod template;
+pub mod trace;
+pub mod xml;
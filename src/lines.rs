@@ -0,0 +1,56 @@
+//! Text split into lines once, so the response pane can draw just the lines
+//! on screen however large the body is.
+
+use std::ops::Range;
+
+/// A text with the byte range of each of its lines, as [`str::lines`]
+/// splits them.
+#[derive(Debug, Clone, Default)]
+pub struct IndexedText {
+    text: String,
+    lines: Vec<Range<usize>>,
+}
+
+impl IndexedText {
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.lines.len()
+    }
+
+    /// Up to `count` lines starting at line `from`, with their numbers.
+    pub fn window(&self, from: usize, count: usize) -> impl Iterator<Item = (usize, &str)> {
+        let from = from.min(self.lines.len());
+        let to = from.saturating_add(count).min(self.lines.len());
+        self.lines[from..to]
+            .iter()
+            .enumerate()
+            .map(move |(i, range)| (from + i, &self.text[range.clone()]))
+    }
+}
+
+impl From<String> for IndexedText {
+    fn from(text: String) -> Self {
+        let mut lines = Vec::new();
+        let mut start = 0;
+        while start < text.len() {
+            let end = text[start..].find('\n').map_or(text.len(), |i| start + i);
+            let line_end = if text[start..end].ends_with('\r') {
+                end - 1
+            } else {
+                end
+            };
+            lines.push(start..line_end);
+            start = end + 1;
+        }
+        Self { text, lines }
+    }
+}
+
+impl From<&str> for IndexedText {
+    fn from(text: &str) -> Self {
+        Self::from(text.to_string())
+    }
+}
@@ -1,189 +1,288 @@
+mod app;
+mod editor;
+mod lines;
+mod nav;
+mod pager;
+mod rows;
+mod search;
+#[cfg(test)]
+mod tests;
+mod ui;
+
 use crossterm::{
-    event::{self, Event, KeyCode},
+    event::{
+        self, Event, KeyboardEnhancementFlags, PopKeyboardEnhancementFlags,
+        PushKeyboardEnhancementFlags,
+    },
     execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    terminal::{
+        disable_raw_mode, enable_raw_mode, supports_keyboard_enhancement, EnterAlternateScreen,
+        LeaveAlternateScreen,
+    },
 };
-use ratatui::{
-    backend::CrosstermBackend,
-    layout::{Alignment, Constraint, Direction, Layout},
-    style::{Color, Modifier, Style},
-    text::{Line, Span},
-    widgets::{Block, Borders, List, ListItem, Paragraph},
-    Terminal,
+use ratatui::{backend::CrosstermBackend, Terminal};
+use std::{
+    env, fs,
+    io::{self, IsTerminal, Read, Write},
+    path::PathBuf,
 };
-use reqwest::blocking::Client;
-use std::{collections::HashMap, io};
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Setup terminal
+use app::App;
+use lazycurl::{
+    baseline, binary, config, history, http,
+    oauth::TokenSource,
+    preferences, recording,
+    request::{self, Request},
+    store, trace,
+};
+
+/// Switches the terminal to the TUI's raw, alternate screen mode.
+fn enter_tui(enhanced_keys: bool) -> io::Result<()> {
     enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen)?;
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
+    execute!(io::stdout(), EnterAlternateScreen)?;
+    if enhanced_keys {
+        execute!(
+            io::stdout(),
+            PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES)
+        )?;
+    }
+    Ok(())
+}
 
-    let mut input = String::new();
-    let mut selected_method = 0;
-    let headers: HashMap<String, String> = HashMap::new();
-    let params: HashMap<String, String> = HashMap::new();
-    let body = String::new();
-    let methods = ["GET", "POST", "PUT", "DELETE", "PATCH"];
-    let client = Client::new();
-    let mut response_text = String::from("Response will appear here...");
-    let mut options_mode = 0; // 0: Headers, 1: Body, 2: Params
-
-    loop {
-        terminal.draw(|frame| {
-            let size = frame.area();
-
-            // Split the UI into left (methods) and right (rest of UI)
-            let main_layout = Layout::default()
-                .direction(Direction::Horizontal)
-                .constraints([
-                    Constraint::Percentage(10), // Left panel for HTTP methods
-                    Constraint::Percentage(90), // Right panel for input, response, etc.
-                ])
-                .split(size);
-
-            // Further split the right panel into vertical sections
-            let right_layout = Layout::default()
-                .direction(Direction::Vertical)
-                .constraints([
-                    Constraint::Percentage(10), // Header
-                    Constraint::Percentage(10), // URL Input
-                    Constraint::Percentage(40), // Additional UI (Future feature)
-                    Constraint::Percentage(40), // Response box
-                ])
-                .split(main_layout[1]);
-
-            // Header
-            let header = Paragraph::new(Line::from(vec![Span::styled(
-                "LazyCurl - HTTP Requester",
-                Style::default()
-                    .fg(Color::Cyan)
-                    .add_modifier(Modifier::BOLD),
-            )]))
-            .block(Block::default().borders(Borders::ALL))
-            .alignment(Alignment::Center);
-
-            // URL Input Box
-            let input_box = Paragraph::new(input.clone())
-                .block(Block::default().title("Enter URL").borders(Borders::ALL))
-                .alignment(Alignment::Center);
-
-            // Method Selector List
-            let methods_items: Vec<ListItem> = methods
-                .iter()
-                .enumerate()
-                .map(|(i, &method)| {
-                    let style = if i == selected_method {
-                        Style::default()
-                            .fg(Color::Yellow)
-                            .add_modifier(Modifier::BOLD)
-                    } else {
-                        Style::default()
-                    };
-                    ListItem::new(Span::styled(method, style))
-                })
-                .collect();
-
-            let method_box = List::new(methods_items)
-                .block(Block::default().title("HTTP Method").borders(Borders::ALL));
-
-            let options_text = if options_mode == 0 {
-                format!("Headers: {:?}", headers)
-            } else if options_mode == 1 {
-                format!("Body: {}", body)
-            } else {
-                format!("Params: {:?}", params)
-            };
-            let options_box = Paragraph::new(options_text).block(
-                Block::default()
-                    .title("Options (H: Headers, B: Body, P: Params)")
-                    .borders(Borders::ALL),
-            );
-
-            // Response Box
-            let response_box = Paragraph::new(response_text.clone())
-                .block(Block::default().title("Response").borders(Borders::ALL));
-
-            // Render UI Components
-            frame.render_widget(method_box, main_layout[0]); // Left panel (Method selector)
-            frame.render_widget(header, right_layout[0]); // Header (Right panel)
-            frame.render_widget(input_box, right_layout[1]); // Input field (Right panel)
-            frame.render_widget(options_box, right_layout[2]); // Input field (Right panel)
-            frame.render_widget(response_box, right_layout[3]); // Response box (Right panel)
-        })?;
+/// Gives the terminal back in the state it was found in.
+fn leave_tui(enhanced_keys: bool) -> io::Result<()> {
+    if enhanced_keys {
+        execute!(io::stdout(), PopKeyboardEnhancementFlags)?;
+    }
+    disable_raw_mode()?;
+    execute!(io::stdout(), LeaveAlternateScreen)
+}
 
-        // Event handling
-        if event::poll(std::time::Duration::from_millis(200))? {
-            if let Event::Key(key) = event::read()? {
-                match key.code {
-                    KeyCode::Esc => break,
-                    KeyCode::Up => {
-                        if selected_method > 0 {
-                            selected_method -= 1;
-                        }
-                    }
-                    KeyCode::Down => {
-                        if selected_method < methods.len() - 1 {
-                            selected_method += 1;
-                        }
-                    }
-                    KeyCode::Char('H') => options_mode = 0,
-                    KeyCode::Char('B') => options_mode = 1,
-                    KeyCode::Char('P') => options_mode = 2,
-                    KeyCode::Enter => {
-                        if !input.is_empty() {
-                            let method = methods[selected_method];
-                            let url = input.clone();
-                            response_text = make_request(&client, method, &url, &headers, &body);
-                        }
-                    }
-                    KeyCode::Char(c) => input.push(c),
-                    KeyCode::Backspace => {
-                        input.pop();
-                    }
-                    _ => {}
-                }
+const USAGE: &str = "Usage: lazycurl [--dir DIR] [--body FILE] [--quiet] [-X METHOD] [URL]
+
+  --dir DIR    Keep config, history and recordings in DIR
+               (default: $LAZYCURL_DIR, then $XDG_CONFIG_HOME/lazycurl)
+  --body FILE  Start with the body read from FILE, or from stdin for -
+  --quiet      Send the request once and print the response body instead
+               of starting the TUI; the default when stdout isn't a terminal
+  -X METHOD    Method of the request (default: GET)
+  URL          Start with, or with --quiet send, a request to URL";
+
+/// What the command line asks for.
+#[derive(Default)]
+struct Args {
+    body: Option<String>, // Where to read the body from
+    quiet: bool,
+    method: Option<usize>,
+    url: Option<String>,
+}
+
+/// Applies the command line arguments, or returns the message to exit with.
+fn parse_args() -> Result<Args, String> {
+    let mut parsed = Args::default();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dir" => {
+                let dir = args.next().ok_or("--dir needs a directory")?;
+                config::set_dir(PathBuf::from(dir));
+            }
+            "--body" => {
+                parsed.body = Some(args.next().ok_or("--body needs a file, or - for stdin")?)
+            }
+            "--quiet" => parsed.quiet = true,
+            "-X" => {
+                let method = args.next().ok_or("-X needs a method")?;
+                parsed.method = Some(
+                    request::method_index(&method.to_ascii_uppercase())
+                        .ok_or_else(|| format!("unsupported method: {}", method))?,
+                );
             }
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                std::process::exit(0);
+            }
+            url if !url.starts_with('-') && parsed.url.is_none() => parsed.url = Some(url.into()),
+            other => return Err(format!("unknown argument: {}\n\n{}", other, USAGE)),
         }
     }
+    Ok(parsed)
+}
 
-    // Restore terminal
-    disable_raw_mode()?;
-    execute!(io::stdout(), LeaveAlternateScreen)?;
+/// Reads the body named by `--body`. Stdin is read to the end here, before
+/// the TUI takes over the terminal; keys are then read from the terminal
+/// itself. Binary contents come back as base64, marked by the `true`.
+fn read_body(source: &str) -> Result<(String, bool), String> {
+    let bytes = if source != "-" {
+        fs::read(source).map_err(|e| format!("{}: {}", source, e))?
+    } else {
+        let mut stdin = io::stdin();
+        if stdin.is_terminal() {
+            return Err("nothing piped to stdin".into());
+        }
+        let mut body = Vec::new();
+        stdin
+            .read_to_end(&mut body)
+            .map_err(|e| format!("stdin: {}", e))?;
+        if body.is_empty() {
+            return Err("stdin was empty".into());
+        }
+        body
+    };
+    Ok(match String::from_utf8(bytes) {
+        Ok(text) => (text, false),
+        Err(err) => (binary::encode(err.as_bytes()), true),
+    })
+}
 
-    Ok(())
+/// Sends `request` once and writes the response body to stdout, without
+/// touching the terminal. Non-2xx statuses are reported on stderr.
+fn send_once(request: &Request) -> Result<(), String> {
+    let layered = config::load();
+    for warning in &layered.warnings {
+        eprintln!("lazycurl: {}", warning);
+    }
+    let config = layered.config;
+    let auth = config.oauth2.clone().map(TokenSource::new);
+    let client = http::client(&config, None)?;
+    let response = http::execute(&client, &config, request, auth.as_ref())?;
+    if !(200..300).contains(&response.status) {
+        eprintln!("lazycurl: {} {}", response.status, response.reason());
+    }
+    io::stdout()
+        .write_all(response.bytes())
+        .map_err(|e| format!("stdout: {}", e))
 }
 
-/// Handles making an HTTP request based on user selection
-fn make_request(
-    client: &Client,
-    method: &str,
-    url: &str,
-    headers: &HashMap<String, String>,
-    body: &str,
-) -> String {
-    let mut request = match method {
-        "GET" => client.get(url),
-        "POST" => client.post(url),
-        "PUT" => client.put(url),
-        "DELETE" => client.delete(url),
-        "PATCH" => client.patch(url),
-        _ => return "Invalid Method".to_string(),
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = parse_args().unwrap_or_else(|message| {
+        eprintln!("{}", message);
+        std::process::exit(2);
+    });
+    let body = args.body.as_deref().map(read_body);
+    // Verbose sends read reqwest's trace records of their connection
+    if log::set_logger(&trace::Capture).is_ok() {
+        log::set_max_level(log::LevelFilter::Trace);
+    }
+
+    let mut request = Request {
+        method: args.method.unwrap_or_default(),
+        url: args.url.clone().unwrap_or_default(),
+        ..Request::default()
     };
+    request.sync_params();
+
+    // Piped or redirected output gets the response, not escape codes
+    if args.quiet || !io::stdout().is_terminal() {
+        if request.url.is_empty() {
+            eprintln!("lazycurl: no URL to send\n\n{}", USAGE);
+            std::process::exit(2);
+        }
+        if let Some(body) = body {
+            (request.body, request.body_base64) = body.unwrap_or_else(|err| {
+                eprintln!("lazycurl: body not loaded: {}", err);
+                std::process::exit(2);
+            });
+        }
+        if let Err(err) = send_once(&request) {
+            eprintln!("lazycurl: {}", err);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
 
-    for (key, value) in headers {
-        request = request.header(key, value);
+    // Setup terminal. Terminals that support it report Ctrl+Enter
+    // distinctly from Enter.
+    let enhanced_keys = matches!(supports_keyboard_enhancement(), Ok(true));
+    enter_tui(enhanced_keys)?;
+    let backend = CrosstermBackend::new(io::stdout());
+    let mut terminal = Terminal::new(backend)?;
+
+    // Problems with persisted files are reported but don't stop startup
+    let mut warnings = Vec::new();
+    if let Err(err) = store::check_writable() {
+        warnings.push(format!(
+            "Not saving history or recordings this session ({}); use --dir or LAZYCURL_DIR",
+            err
+        ));
+    }
+    let layered = config::load();
+    warnings.extend(layered.warnings);
+    let recordings = recording::Recordings::load().unwrap_or_else(|err| {
+        warnings.push(format!("Recordings ignored: {}", err));
+        recording::Recordings::default()
+    });
+    let history = history::History::load().unwrap_or_else(|err| {
+        warnings.push(format!("History ignored: {}", err));
+        history::History::default()
+    });
+
+    let baselines = baseline::Baselines::load().unwrap_or_else(|err| {
+        warnings.push(format!("Baselines ignored: {}", err));
+        baseline::Baselines::default()
+    });
+
+    let preferences = preferences::Preferences::load().unwrap_or_else(|err| {
+        warnings.push(format!("Preferences ignored: {}", err));
+        preferences::Preferences::default()
+    });
+
+    let mut app = App::new(layered.config, recordings, history, baselines)?;
+    app.request = request;
+    app.config_files = layered.loaded;
+    app.preferences = preferences;
+    if app.config.persist_cookie_jars {
+        if let Err(err) = app.jars.load() {
+            warnings.push(format!("Cookie jars ignored: {}", err));
+        }
     }
+    match body {
+        Some(Ok((body, base64))) => {
+            app.request.body = body;
+            app.request.body_base64 = base64;
+            app.options_mode = 1;
+        }
+        Some(Err(err)) => warnings.push(format!("Body not loaded: {}", err)),
+        None => {}
+    }
+    app.status = (!warnings.is_empty()).then(|| warnings.join("; "));
+
+    while !app.should_quit {
+        app.tick();
+        terminal.draw(|frame| ui::draw(frame, &app))?;
+
+        // Event handling
+        // Often enough to show a streaming body and its progress as it arrives
+        let wait = if app.is_in_flight() { 100 } else { 200 };
+        if event::poll(std::time::Duration::from_millis(wait))? {
+            if let Event::Key(key) = event::read()? {
+                app.on_key(key);
+            }
+        }
 
-    if method != "GET" {
-        request = request.body(body.to_string());
+        if let Some(target) = app.edit.take() {
+            let (text, extension) = app.edit_text(target);
+            leave_tui(enhanced_keys)?;
+            let result = editor::edit(&text, extension);
+            enter_tui(enhanced_keys)?;
+            // The editor drew over the screen ratatui thinks is still there
+            terminal.clear()?;
+            app.finish_edit(target, result);
+        }
+
+        if std::mem::take(&mut app.page_response) {
+            leave_tui(enhanced_keys)?;
+            let result = pager::page(app.response_text.as_str());
+            enter_tui(enhanced_keys)?;
+            terminal.clear()?;
+            if let Err(err) = result {
+                app.status = Some(format!("Pager failed: {}", err));
+            }
+        }
     }
 
-    request
-        .send()
-        .and_then(|res| res.text())
-        .unwrap_or_else(|_| "Failed to make request".into())
+    // Restore terminal
+    leave_tui(enhanced_keys)?;
+
+    Ok(())
 }
@@ -0,0 +1,51 @@
+//! List navigation helpers: clamped movement and key-repeat acceleration.
+//!
+//! Terminals deliver a held key as a stream of ordinary key presses, so a
+//! repeat is recognised by the same key arriving again within a short window.
+//! The longer the streak, the larger each step becomes.
+
+use std::time::{Duration, Instant};
+
+use crossterm::event::KeyCode;
+
+use lazycurl::config::Navigation;
+
+/// Repeats needed before each doubling of the step size.
+const REPEATS_PER_DOUBLING: u32 = 8;
+/// Largest step an accelerated key press can move.
+const MAX_STEP: usize = 8;
+
+#[derive(Debug, Default)]
+pub struct KeyRepeat {
+    last: Option<(KeyCode, Instant)>,
+    streak: u32,
+}
+
+impl KeyRepeat {
+    /// Registers a press of `code` at `now` and returns how many rows it
+    /// should move.
+    pub fn step(&mut self, code: KeyCode, now: Instant, config: &Navigation) -> usize {
+        let window = Duration::from_millis(config.repeat_window_ms);
+        let repeated = matches!(self.last, Some((last, at)) if last == code && now.duration_since(at) <= window);
+
+        self.streak = if repeated { self.streak + 1 } else { 0 };
+        self.last = Some((code, now));
+
+        if !config.accelerate {
+            return 1;
+        }
+        let doublings = self.streak / REPEATS_PER_DOUBLING;
+        1usize
+            .checked_shl(doublings)
+            .unwrap_or(MAX_STEP)
+            .min(MAX_STEP)
+    }
+}
+
+/// Moves `index` by `delta` rows within a list of `len` items.
+pub fn move_index(index: usize, delta: isize, len: usize) -> usize {
+    if len == 0 {
+        return 0;
+    }
+    index.saturating_add_signed(delta).min(len - 1)
+}
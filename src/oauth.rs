@@ -0,0 +1,72 @@
+//! OAuth2 access tokens for `{{oauth2.token}}`, fetched with the client
+//! credentials grant described by the `[oauth2]` config section and kept
+//! for the rest of the session.
+
+use std::sync::Mutex;
+
+use reqwest::blocking::Client;
+use serde_json::Value;
+
+use crate::{config::OAuth2, template};
+
+pub struct TokenSource {
+    settings: OAuth2,
+    cached: Mutex<Option<String>>,
+}
+
+impl TokenSource {
+    pub fn new(settings: OAuth2) -> Self {
+        Self {
+            settings,
+            cached: Mutex::new(None),
+        }
+    }
+
+    /// Whether a 401 should get the token refreshed and the request sent
+    /// once more.
+    pub fn retry_on_401(&self) -> bool {
+        self.settings.retry_on_401
+    }
+
+    /// The cached token, fetching one first if there is none yet.
+    pub fn token(&self, client: &Client) -> Result<String, String> {
+        if let Some(token) = self.cached.lock().map_err(|e| e.to_string())?.clone() {
+            return Ok(token);
+        }
+        self.refresh(client)
+    }
+
+    /// Fetches a new token in place of the cached one.
+    pub fn refresh(&self, client: &Client) -> Result<String, String> {
+        let token = self.fetch(client)?;
+        *self.cached.lock().map_err(|e| e.to_string())? = Some(token.clone());
+        Ok(token)
+    }
+
+    fn fetch(&self, client: &Client) -> Result<String, String> {
+        let settings = &self.settings;
+        let client_secret = template::resolve(&settings.client_secret)?;
+        let mut form = vec![
+            ("grant_type", "client_credentials"),
+            ("client_id", settings.client_id.as_str()),
+            ("client_secret", client_secret.as_str()),
+        ];
+        if !settings.scope.is_empty() {
+            form.push(("scope", settings.scope.as_str()));
+        }
+
+        let res = client
+            .post(&settings.token_url)
+            .form(&form)
+            .send()
+            .map_err(|e| format!("token request failed: {}", e))?;
+        let status = res.status();
+        let body: Value = res
+            .json()
+            .map_err(|e| format!("token response ({}) is not JSON: {}", status, e))?;
+        match body.get("access_token").and_then(Value::as_str) {
+            Some(token) => Ok(token.to_string()),
+            None => Err(format!("token response ({}) has no access_token", status)),
+        }
+    }
+}
@@ -0,0 +1,34 @@
+//! Showing text in the user's own pager.
+
+use std::{
+    env,
+    io::Write,
+    process::{Command, Stdio},
+};
+
+/// Pipes `text` into `$PAGER`, or `less`, and waits for it to exit.
+/// The terminal must be handed over to the pager before calling this.
+pub fn page(text: &str) -> Result<(), String> {
+    let command = env::var("PAGER")
+        .ok()
+        .filter(|command| !command.trim().is_empty())
+        .unwrap_or_else(|| "less".into());
+    // Allows pagers that need flags, like `less -S`
+    let mut words = command.split_whitespace();
+    let program = words.next().unwrap_or("less");
+
+    let mut child = Command::new(program)
+        .args(words)
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("{}: {}", program, e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // Quitting before the end closes the pipe, which is no error
+        let _ = stdin.write_all(text.as_bytes());
+    }
+    match child.wait() {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(format!("{} exited with {}", program, status)),
+        Err(err) => Err(format!("{}: {}", program, err)),
+    }
+}
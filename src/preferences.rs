@@ -0,0 +1,27 @@
+//! View choices remembered across sessions, stored as `preferences.json`
+//! in the config directory.
+
+use serde::{Deserialize, Serialize};
+
+use crate::store;
+
+const FILE: &str = "preferences.json";
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Preferences {
+    /// History shows when requests were sent as "3 minutes ago" instead of
+    /// the date and time.
+    pub relative_history_times: bool,
+}
+
+impl Preferences {
+    /// Loads the preferences file; a missing file means the defaults.
+    pub fn load() -> Result<Self, String> {
+        store::load(FILE)
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        store::save(FILE, self)
+    }
+}
@@ -0,0 +1,199 @@
+//! Helpers for showing the query portion of a URL decoded and for
+//! re-encoding it before the request goes out.
+
+/// Bytes that keep their percent-encoding in the decoded view, because
+/// decoding them would change how the query string is split into pairs.
+const DELIMITERS: &[u8] = b"&=#+%";
+
+/// A query parameter as written in the URL; `None` for a bare `name`
+/// without `=`.
+pub type Param = (String, Option<String>);
+
+/// Splits a URL into the part before the query and the query itself
+/// (without the leading `?`). Any fragment stays attached to the query.
+fn split(url: &str) -> (&str, Option<&str>) {
+    match url.find('?') {
+        Some(idx) => (&url[..idx], Some(&url[idx + 1..])),
+        None => (url, None),
+    }
+}
+
+pub fn hex_val(b: u8) -> Option<u8> {
+    match b {
+        b'0'..=b'9' => Some(b - b'0'),
+        b'a'..=b'f' => Some(b - b'a' + 10),
+        b'A'..=b'F' => Some(b - b'A' + 10),
+        _ => None,
+    }
+}
+
+/// Returns `url` with its query string decoded into a human-readable form.
+///
+/// `+` is shown as a space. Encoded delimiters (`%26`, `%3D`, ...) and
+/// escapes that aren't UTF-8 text are left as they are so that [`encode`]
+/// gives back an equivalent query.
+pub fn decode(url: &str) -> String {
+    let (base, Some(query)) = split(url) else {
+        return url.to_string();
+    };
+    format!("{}?{}", base, unescape(query, true, DELIMITERS))
+}
+
+/// `text` with its `%XX` escapes decoded, except escapes of `kept` bytes
+/// and of bytes that don't make up UTF-8, which stay escaped. With `plus`,
+/// `+` becomes a space.
+fn unescape(text: &str, plus: bool, kept: &[u8]) -> String {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut escaped = Vec::new(); // Decoded bytes of the escapes in a row
+    let mut i = 0;
+    while i < bytes.len() {
+        let b = bytes[i];
+        if b == b'%' {
+            if let (Some(hi), Some(lo)) = (
+                bytes.get(i + 1).copied().and_then(hex_val),
+                bytes.get(i + 2).copied().and_then(hex_val),
+            ) {
+                let decoded = (hi << 4) | lo;
+                if kept.contains(&decoded) {
+                    flush_escaped(&mut escaped, &mut out);
+                    out.extend_from_slice(&bytes[i..i + 3]);
+                } else {
+                    escaped.push(decoded);
+                }
+                i += 3;
+                continue;
+            }
+        }
+        flush_escaped(&mut escaped, &mut out);
+        out.push(if plus && b == b'+' { b' ' } else { b });
+        i += 1;
+    }
+    flush_escaped(&mut escaped, &mut out);
+    // Only whole characters and escapes went in
+    String::from_utf8(out)
+        .unwrap_or_else(|err| String::from_utf8_lossy(err.as_bytes()).into_owned())
+}
+
+/// Moves decoded `escaped` bytes to `out`, escaping again those that aren't
+/// part of a UTF-8 character.
+fn flush_escaped(escaped: &mut Vec<u8>, out: &mut Vec<u8>) {
+    for chunk in escaped.utf8_chunks() {
+        out.extend_from_slice(chunk.valid().as_bytes());
+        for b in chunk.invalid() {
+            out.extend_from_slice(format!("%{:02X}", b).as_bytes());
+        }
+    }
+    escaped.clear();
+}
+
+/// Returns `url` with every character of its query string that is not
+/// allowed on the wire percent-encoded. Existing `%XX` escapes are kept.
+pub fn encode(url: &str) -> String {
+    let (base, Some(query)) = split(url) else {
+        return url.to_string();
+    };
+
+    let bytes = query.as_bytes();
+    let mut out = String::with_capacity(bytes.len());
+    for (i, &b) in bytes.iter().enumerate() {
+        let is_escape = b == b'%'
+            && bytes.get(i + 1).copied().and_then(hex_val).is_some()
+            && bytes.get(i + 2).copied().and_then(hex_val).is_some();
+        if is_escape || b.is_ascii_alphanumeric() || b"-._~!$'()*,;:@/?&=+#".contains(&b) {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{:02X}", b));
+        }
+    }
+
+    format!("{}?{}", base, out)
+}
+
+/// The query string of `url` and its fragment (with the `#`), if any.
+pub fn query_and_fragment(url: &str) -> (Option<&str>, &str) {
+    let (_, query) = split(url);
+    match query {
+        Some(query) => match query.find('#') {
+            Some(idx) => (Some(&query[..idx]), &query[idx..]),
+            None => (Some(query), ""),
+        },
+        None => (None, url.find('#').map_or("", |idx| &url[idx..])),
+    }
+}
+
+/// Splits a query string into its parameters, in order and keeping
+/// duplicates. Names and values stay encoded as they are.
+pub fn parse_params(query: &str) -> Vec<Param> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| match pair.split_once('=') {
+            Some((name, value)) => (name.to_string(), Some(value.to_string())),
+            None => (pair.to_string(), None),
+        })
+        .collect()
+}
+
+/// Joins parameters back into a query string.
+pub fn join_params(params: &[Param]) -> String {
+    params
+        .iter()
+        .map(|(name, value)| match value {
+            Some(value) => format!("{}={}", name, value),
+            None => name.clone(),
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// `text` with every `%XX` escape decoded; escapes of bytes that aren't
+/// UTF-8 text stay as they are.
+pub fn percent_decode(text: &str) -> String {
+    unescape(text, false, &[])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decoding_keeps_delimiters_and_bytes_that_are_not_text() {
+        let url = "http://x/?q=caf%C3%A9+au+lait&a%26b=%3D&raw=%FF%FEok&cut=%C3";
+        let decoded = decode(url);
+        assert_eq!(
+            decoded,
+            "http://x/?q=café au lait&a%26b=%3D&raw=%FF%FEok&cut=%C3"
+        );
+        assert_eq!(
+            encode(&decoded),
+            "http://x/?q=caf%C3%A9%20au%20lait&a%26b=%3D&raw=%FF%FEok&cut=%C3"
+        );
+        assert_eq!(encode(&decode("http://x/?a=%FF")), "http://x/?a=%FF");
+    }
+
+    #[test]
+    fn percent_decoding_decodes_everything_but_invalid_text() {
+        assert_eq!(percent_decode("na%C3%AFve%2B%ZZ%E2%82"), "naïve+%ZZ%E2%82");
+    }
+
+    #[test]
+    fn params_split_and_join_back_unchanged() {
+        let params = parse_params("a=1&&flag&b=x%20y&a=2");
+        assert_eq!(
+            params,
+            [
+                ("a".to_string(), Some("1".to_string())),
+                ("flag".to_string(), None),
+                ("b".to_string(), Some("x%20y".to_string())),
+                ("a".to_string(), Some("2".to_string())),
+            ]
+        );
+        assert_eq!(join_params(&params), "a=1&flag&b=x%20y&a=2");
+        assert_eq!(
+            query_and_fragment("http://x/?a=1#top"),
+            (Some("a=1"), "#top")
+        );
+        assert_eq!(query_and_fragment("http://x/#top"), (None, "#top"));
+    }
+}
@@ -0,0 +1,53 @@
+//! Recorded request/response pairs that can be replayed without touching
+//! the network, stored as `recordings.json` in the config directory.
+//!
+//! Recordings are keyed by the request exactly as typed, before placeholders
+//! are resolved, so secrets never end up in the file.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{request::Request, response::Response, store};
+
+const FILE: &str = "recordings.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Recording {
+    request: Request,
+    response: Response,
+}
+
+#[derive(Debug, Default)]
+pub struct Recordings {
+    entries: Vec<Recording>,
+}
+
+impl Recordings {
+    /// Loads the recordings file; a missing file means no recordings.
+    pub fn load() -> Result<Self, String> {
+        store::load(FILE).map(|entries| Self { entries })
+    }
+
+    /// Stores `response` for `request`, replacing an earlier recording of
+    /// the same request, and writes the file.
+    pub fn record(&mut self, request: &Request, response: &Response) -> Result<(), String> {
+        self.entries
+            .retain(|entry| !entry.request.same_exchange(request));
+        self.entries.push(Recording {
+            request: request.clone(),
+            response: response.clone(),
+        });
+        self.save()
+    }
+
+    /// The recorded response for `request`, if there is one.
+    pub fn replay(&self, request: &Request) -> Option<&Response> {
+        self.entries
+            .iter()
+            .find(|entry| entry.request.same_exchange(request))
+            .map(|entry| &entry.response)
+    }
+
+    fn save(&self) -> Result<(), String> {
+        store::save(FILE, &self.entries)
+    }
+}
@@ -0,0 +1,126 @@
+//! Redirect policy that stops as soon as a URL repeats, so that loops are
+//! reported as such instead of surfacing as a generic redirect-limit error.
+
+use std::{error::Error, fmt};
+
+use reqwest::{redirect::Policy, Url};
+
+/// Same limit reqwest uses for its default policy.
+const MAX_REDIRECTS: usize = 10;
+
+/// Why the redirect chain was cut short, along with the URLs visited so far.
+#[derive(Debug)]
+pub enum RedirectError {
+    /// The server redirected back to a URL already in the chain.
+    Loop { url: Url, chain: Vec<Url> },
+    /// The chain grew past [`MAX_REDIRECTS`] without repeating.
+    TooMany { chain: Vec<Url> },
+}
+
+impl RedirectError {
+    fn chain(&self) -> &[Url] {
+        match self {
+            RedirectError::Loop { chain, .. } | RedirectError::TooMany { chain } => chain,
+        }
+    }
+
+    /// Finds a `RedirectError` among the sources of an error returned by reqwest.
+    pub fn find<'a>(err: &'a (dyn Error + 'static)) -> Option<&'a RedirectError> {
+        let mut current = Some(err);
+        while let Some(err) = current {
+            if let Some(redirect) = err.downcast_ref::<RedirectError>() {
+                return Some(redirect);
+            }
+            current = err.source();
+        }
+        None
+    }
+}
+
+impl fmt::Display for RedirectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RedirectError::Loop { url, .. } => write!(f, "redirect loop detected at {}", url)?,
+            RedirectError::TooMany { .. } => {
+                write!(f, "stopped after {} redirects", MAX_REDIRECTS)?
+            }
+        }
+        writeln!(f)?;
+        writeln!(f, "Redirect chain:")?;
+        for (i, url) in self.chain().iter().enumerate() {
+            writeln!(f, "  {}. {}", i + 1, url)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for RedirectError {}
+
+/// Builds the redirect policy used by the client.
+pub fn policy() -> Policy {
+    Policy::custom(|attempt| {
+        let mut chain = attempt.previous().to_vec();
+        chain.push(attempt.url().clone());
+
+        if attempt.previous().contains(attempt.url()) {
+            let url = attempt.url().clone();
+            attempt.error(RedirectError::Loop { url, chain })
+        } else if attempt.previous().len() > MAX_REDIRECTS {
+            attempt.error(RedirectError::TooMany { chain })
+        } else {
+            attempt.follow()
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct Wrapper(RedirectError);
+
+    impl fmt::Display for Wrapper {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("error following redirect")
+        }
+    }
+
+    impl Error for Wrapper {
+        fn source(&self) -> Option<&(dyn Error + 'static)> {
+            Some(&self.0)
+        }
+    }
+
+    fn url(path: &str) -> Url {
+        Url::parse(&format!("http://x{}", path)).unwrap()
+    }
+
+    #[test]
+    fn loops_list_the_chain_that_led_to_them() {
+        let err = RedirectError::Loop {
+            url: url("/a"),
+            chain: vec![url("/a"), url("/b"), url("/a")],
+        };
+        assert_eq!(
+            err.to_string(),
+            "redirect loop detected at http://x/a\nRedirect chain:\n  \
+             1. http://x/a\n  2. http://x/b\n  3. http://x/a\n"
+        );
+        let err = RedirectError::TooMany {
+            chain: vec![url("/1")],
+        };
+        assert!(err.to_string().starts_with("stopped after 10 redirects\n"));
+    }
+
+    #[test]
+    fn redirect_errors_are_found_among_sources() {
+        let wrapped = Wrapper(RedirectError::TooMany { chain: Vec::new() });
+        assert!(matches!(
+            RedirectError::find(&wrapped),
+            Some(RedirectError::TooMany { .. })
+        ));
+        let unrelated = std::io::Error::other("refused");
+        assert!(RedirectError::find(&unrelated).is_none());
+    }
+}
@@ -0,0 +1,124 @@
+//! The request being edited, independent of how it is displayed or sent.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    binary,
+    query::{self, Param},
+};
+
+/// Methods offered in the method selector, in display order.
+pub const METHODS: [&str; 5] = ["GET", "POST", "PUT", "DELETE", "PATCH"];
+
+/// A single HTTP request as the user describes it.
+///
+/// Fields hold exactly what was typed: placeholders are not resolved and the
+/// query string is not re-encoded until the request is sent.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Request {
+    pub method: usize, // Index into METHODS
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    /// The URL's query parameters, kept in sync with it by
+    /// [`Request::sync_params`] and [`Request::apply_params`].
+    pub params: Vec<Param>,
+    pub body: String,
+    /// The body is base64, sent as the bytes it decodes to.
+    #[serde(default)]
+    pub body_base64: bool,
+    /// `Cookie` header sent instead of the cookie jar's when not empty.
+    /// Requests with an override neither read nor update the jar.
+    #[serde(default)]
+    pub cookies: String,
+    /// `Host` header sent instead of the URL's host when not empty.
+    #[serde(default)]
+    pub host: String,
+    /// TLS server name (SNI) used instead of the URL's host when not
+    /// empty. The connection still goes to the URL's host.
+    #[serde(default)]
+    pub sni: String,
+    /// Response body the request is documented to return, diffed against
+    /// the actual one. Never sent.
+    #[serde(default)]
+    pub expected: String,
+}
+
+impl Request {
+    pub fn method_name(&self) -> &'static str {
+        METHODS[self.method]
+    }
+
+    /// Whether the body goes out with the request: GETs are sent without.
+    pub fn sends_body(&self) -> bool {
+        self.method_name() != "GET"
+    }
+
+    /// Rebuilds the params from the URL's query string.
+    pub fn sync_params(&mut self) {
+        self.params = query::query_and_fragment(&self.url)
+            .0
+            .map(query::parse_params)
+            .unwrap_or_default();
+    }
+
+    /// Rewrites the URL's query string from the params, keeping the rest of
+    /// the URL as it is.
+    pub fn apply_params(&mut self) {
+        let fragment = query::query_and_fragment(&self.url).1.to_string();
+        let base_len = self
+            .url
+            .find('?')
+            .unwrap_or(self.url.len() - fragment.len());
+        self.url.truncate(base_len);
+        if !self.params.is_empty() {
+            self.url.push('?');
+            self.url.push_str(&query::join_params(&self.params));
+        }
+        self.url.push_str(&fragment);
+    }
+
+    /// Whether `other` sends the same request, whatever response each
+    /// expects.
+    pub fn same_exchange(&self, other: &Request) -> bool {
+        let unexpected = |request: &Request| Request {
+            expected: String::new(),
+            ..request.clone()
+        };
+        unexpected(self) == unexpected(other)
+    }
+
+    /// The body as sent: decoded when it's base64.
+    pub fn body_bytes(&self) -> Result<Vec<u8>, String> {
+        if self.body_base64 {
+            binary::decode(&self.body).map_err(|err| format!("body is not valid base64: {}", err))
+        } else {
+            Ok(self.body.clone().into_bytes())
+        }
+    }
+
+    /// Value of the first header called `name`, ignoring case.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Replaces the value of the header called `name`, ignoring case, or
+    /// adds it if there is none.
+    pub fn set_header(&mut self, name: &str, value: &str) {
+        match self
+            .headers
+            .iter_mut()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+        {
+            Some((_, v)) => *v = value.to_string(),
+            None => self.headers.push((name.to_string(), value.to_string())),
+        }
+    }
+}
+
+/// Looks up the index of `name` in [`METHODS`], ignoring case.
+pub fn method_index(name: &str) -> Option<usize> {
+    METHODS.iter().position(|m| m.eq_ignore_ascii_case(name))
+}
@@ -0,0 +1,391 @@
+//! The response to a sent request and helpers for presenting it.
+
+use std::{net::SocketAddr, time::Duration};
+
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::xml;
+
+/// Longest value shown for a single summary key before it is cut off.
+const SUMMARY_VALUE_MAX: usize = 40;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Response {
+    pub status: u16,
+    #[serde(default)]
+    pub headers: Vec<(String, String)>,
+    /// The body as text, with bytes that aren't UTF-8 replaced.
+    pub body: String,
+    /// The body as received, kept only when `body` had to replace bytes.
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "base64")]
+    pub raw: Option<Vec<u8>>,
+    pub elapsed: Duration,
+    /// Where the response came from, after any redirects.
+    #[serde(default)]
+    pub url: String,
+    /// Served from a recording instead of the network.
+    #[serde(skip)]
+    pub replayed: bool,
+    /// Answer to a second try after a 401 got the OAuth2 token refreshed.
+    #[serde(skip)]
+    pub auth_retried: bool,
+    /// Connection it came over, `None` when unknown, e.g. for replays.
+    #[serde(skip)]
+    pub connection: Option<Connection>,
+    /// How the body's actual encoding differed from its `Content-Encoding`.
+    #[serde(skip)]
+    pub encoding_note: Option<String>,
+    /// Size of the server's DER certificate, known for verbose sends.
+    #[serde(skip)]
+    pub certificate_size: Option<usize>,
+}
+
+/// Both ends of a TCP connection; a reused one keeps its local port.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Connection {
+    pub local: SocketAddr,
+    pub remote: SocketAddr,
+}
+
+impl Response {
+    /// `bytes` as the body: the text shown, and the bytes themselves when
+    /// the text can't stand for them.
+    pub fn body_from(bytes: Vec<u8>) -> (String, Option<Vec<u8>>) {
+        match String::from_utf8(bytes) {
+            Ok(text) => (text, None),
+            Err(err) => {
+                let bytes = err.into_bytes();
+                (String::from_utf8_lossy(&bytes).into_owned(), Some(bytes))
+            }
+        }
+    }
+
+    /// The body exactly as received.
+    pub fn bytes(&self) -> &[u8] {
+        self.raw.as_deref().unwrap_or(self.body.as_bytes())
+    }
+
+    /// Value of the first header called `name`, ignoring case.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Everything but the body, for scripts: status, final URL, headers,
+    /// time taken and sizes in bytes.
+    pub fn metadata(&self) -> Value {
+        let headers: Vec<Value> = self
+            .headers
+            .iter()
+            .map(|(name, value)| json!({ "name": name, "value": value }))
+            .collect();
+        let header_bytes: usize = self
+            .headers
+            .iter()
+            .map(|(name, value)| name.len() + value.len() + 4) // ": " and CRLF
+            .sum();
+        json!({
+            "status": self.status,
+            "reason": self.reason(),
+            "url": self.url,
+            "headers": headers,
+            "elapsed_ms": self.elapsed.as_millis() as u64,
+            "size": { "headers": header_bytes, "body": self.bytes().len() },
+        })
+    }
+
+    /// Canonical reason phrase for the status code, e.g. `No Content`.
+    pub fn reason(&self) -> &'static str {
+        StatusCode::from_u16(self.status)
+            .ok()
+            .and_then(|status| status.canonical_reason())
+            .unwrap_or("")
+    }
+
+    /// Text for the response pane. Responses without a body get an explicit
+    /// note with the status and headers instead of an empty pane.
+    pub fn display_text(&self) -> String {
+        if let Some(text) = self.problem_text() {
+            return text;
+        }
+        if !self.body.is_empty() {
+            let is_xml = self.header("content-type").is_some_and(xml::is_xml);
+            let pretty = if is_xml {
+                xml::pretty(&self.body).ok()
+            } else {
+                pretty_json(&self.body)
+                    .or_else(|| jsonp(&self.body).and_then(|(_, json)| pretty_json(json)))
+            };
+            return pretty.unwrap_or_else(|| self.body.clone());
+        }
+
+        let what = match self.status {
+            204 | 304 => format!("{} ({})", self.reason(), self.status),
+            _ => format!("Empty body ({} {})", self.status, self.reason()),
+        };
+        let mut text = format!("{}\n\nHeaders:\n", what);
+        for (name, value) in &self.headers {
+            text.push_str(&format!("  {}: {}\n", name, value));
+        }
+        text
+    }
+
+    /// Whether the body is an RFC 7807 `application/problem+json` document.
+    pub fn is_problem(&self) -> bool {
+        self.header("content-type")
+            .and_then(|value| value.split(';').next())
+            .is_some_and(|mime| mime.trim().eq_ignore_ascii_case("application/problem+json"))
+    }
+
+    /// The standard members of a problem document laid out one per line,
+    /// followed by any extension members. `None` if the body isn't one.
+    fn problem_text(&self) -> Option<String> {
+        if !self.is_problem() {
+            return None;
+        }
+        let Ok(Value::Object(object)) = serde_json::from_str::<Value>(&self.body) else {
+            return None;
+        };
+
+        let mut text = String::new();
+        for (key, label) in PROBLEM_MEMBERS {
+            if let Some(value) = object.get(key) {
+                text.push_str(&format!("{:<10}{}\n", label, plain(value)));
+            }
+        }
+        let extensions: Vec<_> = object
+            .iter()
+            .filter(|(key, _)| !PROBLEM_MEMBERS.iter().any(|(k, _)| k == key))
+            .collect();
+        if !extensions.is_empty() {
+            text.push_str("\nExtensions:\n");
+            for (key, value) in extensions {
+                text.push_str(&format!("  {}: {}\n", key, plain(value)));
+            }
+        }
+        Some(text)
+    }
+
+    /// Rows of a CSV or TSV body, header row first, with line breaks inside
+    /// fields flattened to spaces. `None` for other content types and for
+    /// bodies that don't parse into rows of equal length.
+    pub fn table(&self) -> Option<Vec<Vec<String>>> {
+        let mime = self.header("content-type")?.split(';').next()?.trim();
+        let delimiter = if mime.eq_ignore_ascii_case("text/csv") {
+            b','
+        } else if mime.eq_ignore_ascii_case("text/tab-separated-values") {
+            b'\t'
+        } else {
+            return None;
+        };
+
+        let rows = csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .has_headers(false)
+            .from_reader(self.body.as_bytes())
+            .records()
+            .map(|record| {
+                record.map(|r| {
+                    r.iter()
+                        .map(|field| field.replace(['\r', '\n'], " "))
+                        .collect()
+                })
+            })
+            .collect::<Result<Vec<Vec<String>>, _>>()
+            .ok()?;
+        (!rows.is_empty()).then_some(rows)
+    }
+
+    /// Builds a `key: value` summary of the `keys` present at the top level
+    /// of a JSON object body, or `None` if the body isn't one or has none of
+    /// them.
+    pub fn summary(&self, keys: &[String]) -> Option<String> {
+        let body = jsonp(&self.body).map_or(self.body.as_str(), |(_, json)| json);
+        let Ok(Value::Object(object)) = serde_json::from_str::<Value>(body) else {
+            return None;
+        };
+
+        let parts: Vec<String> = keys
+            .iter()
+            .filter_map(|key| object.get(key).map(|value| (key, value)))
+            .map(|(key, value)| format!("{}: {}", key, summary_value(value)))
+            .collect();
+
+        (!parts.is_empty()).then(|| parts.join(" | "))
+    }
+}
+
+/// Standard problem document members and their labels, in display order.
+const PROBLEM_MEMBERS: [(&str, &str); 5] = [
+    ("title", "Title:"),
+    ("status", "Status:"),
+    ("detail", "Detail:"),
+    ("type", "Type:"),
+    ("instance", "Instance:"),
+];
+
+/// A JSON value as text, strings without their quotes.
+fn plain(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn summary_value(value: &Value) -> String {
+    let text = plain(value);
+    if text.chars().count() > SUMMARY_VALUE_MAX {
+        let cut: String = text.chars().take(SUMMARY_VALUE_MAX).collect();
+        format!("{}…", cut)
+    } else {
+        text
+    }
+}
+
+/// `body` re-indented, if it is JSON.
+pub fn pretty_json(body: &str) -> Option<String> {
+    let value: Value = serde_json::from_str(body).ok()?;
+    serde_json::to_string_pretty(&value).ok()
+}
+
+/// The callback and the JSON it's called with, if `body` is JSONP such as
+/// `callback({...});`, possibly after the `/**/` some servers put first.
+pub fn jsonp(body: &str) -> Option<(&str, &str)> {
+    let text = body.trim();
+    let text = text.strip_prefix("/**/").unwrap_or(text).trim_start();
+    let (callback, rest) = text.split_once('(')?;
+    let callback = callback.trim_end();
+    let mut chars = callback.chars();
+    let is_name = chars
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || matches!(c, '_' | '$'))
+        && chars.all(|c| c.is_alphanumeric() || matches!(c, '_' | '$' | '.'));
+    if !is_name {
+        return None;
+    }
+    let rest = rest.trim_end();
+    let rest = rest.strip_suffix(';').unwrap_or(rest).trim_end();
+    let json = rest.strip_suffix(')')?.trim();
+    serde_json::from_str::<Value>(json)
+        .is_ok()
+        .then_some((callback, json))
+}
+
+/// `text` indented like [`pretty_json`] with the keys of every object
+/// sorted, if it is JSON. Arrays keep their order.
+pub fn sorted_json(text: &str) -> Option<String> {
+    let mut value: Value = serde_json::from_str(text).ok()?;
+    value.sort_all_objects();
+    serde_json::to_string_pretty(&value).ok()
+}
+
+/// `body` indented like [`pretty_json`], with objects and arrays nested
+/// deeper than `depth` levels shown collapsed. `None` if it isn't JSON.
+pub fn folded_json(body: &str, depth: usize) -> Option<String> {
+    let value: Value = serde_json::from_str(body).ok()?;
+    let mut out = String::new();
+    write_folded(&value, 0, depth, &mut out);
+    Some(out)
+}
+
+fn write_folded(value: &Value, level: usize, depth: usize, out: &mut String) {
+    let indent = "  ".repeat(level + 1);
+    match value {
+        Value::Object(object) if object.is_empty() => out.push_str("{}"),
+        Value::Array(items) if items.is_empty() => out.push_str("[]"),
+        Value::Object(object) if level >= depth => {
+            out.push_str(&format!("{{…}} ({})", count(object.len(), "key")))
+        }
+        Value::Array(items) if level >= depth => {
+            out.push_str(&format!("[…] ({})", count(items.len(), "item")))
+        }
+        Value::Object(object) => {
+            out.push_str("{\n");
+            for (i, (key, value)) in object.iter().enumerate() {
+                out.push_str(&format!("{}{}: ", indent, Value::from(key.as_str())));
+                write_folded(value, level + 1, depth, out);
+                out.push_str(if i + 1 < object.len() { ",\n" } else { "\n" });
+            }
+            out.push_str(&format!("{}}}", "  ".repeat(level)));
+        }
+        Value::Array(items) => {
+            out.push_str("[\n");
+            for (i, item) in items.iter().enumerate() {
+                out.push_str(&indent);
+                write_folded(item, level + 1, depth, out);
+                out.push_str(if i + 1 < items.len() { ",\n" } else { "\n" });
+            }
+            out.push_str(&format!("{}]", "  ".repeat(level)));
+        }
+        scalar => out.push_str(&scalar.to_string()),
+    }
+}
+
+fn count(n: usize, noun: &str) -> String {
+    format!("{} {}{}", n, noun, if n == 1 { "" } else { "s" })
+}
+
+/// Raw bodies in recordings, as base64 text.
+mod base64 {
+    use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+    use crate::binary;
+
+    pub fn serialize<S: Serializer>(
+        bytes: &Option<Vec<u8>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        bytes.as_deref().map(binary::encode).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Vec<u8>>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|text| binary::decode(&text).map_err(D::Error::custom))
+            .transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bodies_that_are_not_utf8_keep_their_bytes() {
+        let (body, raw) = Response::body_from(b"%PDF\xff\xfe".to_vec());
+        let response = Response {
+            body,
+            raw,
+            ..Response::default()
+        };
+        assert_eq!(response.body, "%PDF\u{fffd}\u{fffd}");
+        assert_eq!(response.bytes(), b"%PDF\xff\xfe");
+        let recorded: Response =
+            serde_json::from_str(&serde_json::to_string(&response).unwrap()).unwrap();
+        assert_eq!(recorded.bytes(), b"%PDF\xff\xfe");
+
+        let (body, raw) = Response::body_from("café".into());
+        assert_eq!((body.as_str(), raw), ("café", None));
+    }
+
+    #[test]
+    fn jsonp_bodies_are_unwrapped_only_around_json() {
+        assert_eq!(
+            jsonp("/**/ typeof x; cb({\"a\": [1]});"),
+            None,
+            "only a single call is JSONP"
+        );
+        assert_eq!(
+            jsonp("/**/ jQuery.cb_1( {\"a\": [1]} );\n"),
+            Some(("jQuery.cb_1", "{\"a\": [1]}"))
+        );
+        assert_eq!(jsonp("cb([1, 2])"), Some(("cb", "[1, 2]")));
+        assert_eq!(jsonp("alert(document.cookie)"), None);
+        assert_eq!(jsonp("{\"a\": \"f(1)\"}"), None);
+    }
+}
@@ -0,0 +1,56 @@
+//! Line-per-row editing for the headers and params editors.
+//!
+//! Typing always goes to the end of the selected row; the arrows pick the
+//! row.
+
+#[derive(Debug, Default)]
+pub struct RowEditor {
+    pub rows: Vec<String>,
+    pub selected: usize,
+}
+
+impl RowEditor {
+    /// Starts editing `rows` with the last one selected and an empty one
+    /// standing in for none.
+    pub fn new(rows: Vec<String>) -> Self {
+        let rows = if rows.is_empty() {
+            vec![String::new()]
+        } else {
+            rows
+        };
+        Self {
+            selected: rows.len() - 1,
+            rows,
+        }
+    }
+
+    pub fn push(&mut self, c: char) {
+        self.rows[self.selected].push(c);
+    }
+
+    /// Deletes the last character of the selected row, or the row itself
+    /// once it is empty.
+    pub fn pop(&mut self) {
+        if self.rows[self.selected].pop().is_none() && self.rows.len() > 1 {
+            self.rows.remove(self.selected);
+            self.selected = self.selected.saturating_sub(1);
+        }
+    }
+
+    /// Adds an empty row after the selected one and selects it.
+    pub fn new_row(&mut self) {
+        self.selected += 1;
+        self.rows.insert(self.selected, String::new());
+    }
+
+    /// Copies the selected row right after itself and selects the copy.
+    pub fn duplicate(&mut self) {
+        let copy = self.rows[self.selected].clone();
+        self.selected += 1;
+        self.rows.insert(self.selected, copy);
+    }
+
+    pub fn select(&mut self, index: usize) {
+        self.selected = index.min(self.rows.len() - 1);
+    }
+}
@@ -0,0 +1,251 @@
+//! Writing responses to disk under a name that matches their content.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Local};
+use serde_json::json;
+
+use crate::{query, request::Request, response::Response};
+
+/// File extension for a `Content-Type` value, ignoring its parameters.
+pub fn extension_for(content_type: &str) -> &'static str {
+    let mime = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+
+    match mime.as_str() {
+        "text/html" => "html",
+        "text/csv" => "csv",
+        "text/tab-separated-values" => "tsv",
+        "text/css" => "css",
+        "text/javascript" | "application/javascript" => "js",
+        "application/pdf" => "pdf",
+        "application/zip" => "zip",
+        "image/png" => "png",
+        "image/jpeg" => "jpg",
+        "image/gif" => "gif",
+        "image/svg+xml" => "svg",
+        m if m == "application/json" || m.ends_with("+json") => "json",
+        m if m.ends_with("/xml") || m.ends_with("+xml") => "xml",
+        m if m.starts_with("text/") => "txt",
+        _ => "bin",
+    }
+}
+
+/// File name suggested by a `Content-Disposition: attachment` header,
+/// preferring the RFC 5987 `filename*` form. Directories are stripped so the
+/// name can't point outside the current one.
+pub fn attachment_name(response: &Response) -> Option<String> {
+    let header = response.header("content-disposition")?;
+    let mut parts = header.split(';').map(str::trim);
+    if !parts.next()?.eq_ignore_ascii_case("attachment") {
+        return None;
+    }
+
+    let mut plain = None;
+    let mut extended = None;
+    for part in parts {
+        let Some((key, value)) = part.split_once('=') else {
+            continue;
+        };
+        match key.trim().to_ascii_lowercase().as_str() {
+            "filename" => plain = Some(value.trim().trim_matches('"').to_string()),
+            "filename*" => {
+                // charset'language'percent-encoded-name
+                let Some(encoded) = value.trim().splitn(3, '\'').nth(2) else {
+                    continue;
+                };
+                extended = Some(query::percent_decode(encoded));
+            }
+            _ => {}
+        }
+    }
+
+    let name = extended.or(plain)?;
+    let name = name.rsplit(['/', '\\']).next().unwrap_or_default();
+    (!matches!(name, "" | "." | "..")).then(|| name.to_string())
+}
+
+/// The attachment's own name, or a fresh one like
+/// `response-20240131-154501.json` for `response`.
+pub fn default_file_name(response: &Response) -> String {
+    if let Some(name) = attachment_name(response) {
+        return name;
+    }
+    let extension = extension_for(response.header("content-type").unwrap_or_default());
+    format!(
+        "response-{}.{}",
+        Local::now().format("%Y%m%d-%H%M%S"),
+        extension
+    )
+}
+
+/// Writes the body of `response` to `path`.
+pub fn save(response: &Response, path: &Path) -> Result<(), String> {
+    fs::write(path, response.bytes()).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// The request a saved response answered, and when.
+pub struct Echo<'a> {
+    pub request: &'a Request,
+    pub received_at: DateTime<Local>,
+}
+
+/// Writes `response` to `path` like [`save`], noting what produced it: in a
+/// comment at the top of text formats that have comments, otherwise in a
+/// `.meta.json` file next to it, whose path is returned.
+pub fn save_with_echo(
+    response: &Response,
+    path: &Path,
+    echo: &Echo,
+) -> Result<Option<PathBuf>, String> {
+    let request = echo.request;
+    let mut lines = vec![
+        "Saved by LazyCurl".to_string(),
+        format!("{} {}", request.method_name(), request.url),
+    ];
+    lines.extend(request.headers.iter().map(|(k, v)| format!("{}: {}", k, v)));
+    lines.push(format!(
+        "Received {}, status {}",
+        echo.received_at.format("%Y-%m-%d %H:%M:%S"),
+        response.status
+    ));
+
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default();
+    let comment = match extension.to_ascii_lowercase().as_str() {
+        "txt" | "log" => Some(
+            lines
+                .iter()
+                .map(|l| format!("# {}\n", l))
+                .collect::<String>(),
+        ),
+        "html" | "htm" => Some(format!("<!--\n{}\n-->\n", lines.join("\n")))
+            .filter(|_| !lines.iter().any(|l| l.contains("--"))),
+        "css" | "js" => Some(format!("/*\n{}\n*/\n", lines.join("\n")))
+            .filter(|_| !lines.iter().any(|l| l.contains("*/"))),
+        _ => None,
+    };
+    let write_error = |path: &Path, e: std::io::Error| format!("{}: {}", path.display(), e);
+    if let Some(comment) = comment {
+        let mut bytes = comment.into_bytes();
+        bytes.extend_from_slice(response.bytes());
+        return fs::write(path, bytes)
+            .map(|()| None)
+            .map_err(|e| write_error(path, e));
+    }
+
+    save(response, path)?;
+    let mut meta_path = path.as_os_str().to_owned();
+    meta_path.push(".meta.json");
+    let meta_path = PathBuf::from(meta_path);
+    let meta = json!({
+        "method": request.method_name(),
+        "url": request.url,
+        "headers": request.headers,
+        "received_at": echo.received_at.to_rfc3339(),
+        "status": response.status,
+    });
+    let text = serde_json::to_string_pretty(&meta).map_err(|e| e.to_string())?;
+    fs::write(&meta_path, text).map_err(|e| write_error(&meta_path, e))?;
+    Ok(Some(meta_path))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process;
+
+    use super::*;
+
+    #[test]
+    fn saved_files_hold_the_bytes_received() {
+        let received = b"\x89PNG\r\n\x1a\n\x00\xff".to_vec();
+        let (body, raw) = Response::body_from(received.clone());
+        let response = Response {
+            headers: vec![("Content-Type".into(), "image/png".into())],
+            body,
+            raw,
+            ..Response::default()
+        };
+        assert!(default_file_name(&response).ends_with(".png"));
+
+        let path = std::env::temp_dir().join(format!("lazycurl-save-{}.png", process::id()));
+        save(&response, &path).unwrap();
+        let saved = fs::read(&path);
+        let _ = fs::remove_file(&path);
+        assert_eq!(saved.unwrap(), received);
+    }
+
+    #[test]
+    fn echoes_leave_the_bytes_received_alone() {
+        let received = b"\x00\xffdata".to_vec();
+        let (body, raw) = Response::body_from(received.clone());
+        let response = Response {
+            status: 200,
+            body,
+            raw,
+            ..Response::default()
+        };
+        let request = Request {
+            url: "http://x/file".into(),
+            ..Request::default()
+        };
+        let echo = Echo {
+            request: &request,
+            received_at: Local::now(),
+        };
+        let dir = std::env::temp_dir();
+        let bin = dir.join(format!("lazycurl-echo-{}.bin", process::id()));
+        let txt = dir.join(format!("lazycurl-echo-{}.txt", process::id()));
+
+        let meta = save_with_echo(&response, &bin, &echo).unwrap();
+        let saved = fs::read(&bin);
+        let commented = save_with_echo(&response, &txt, &echo).unwrap();
+        let saved_text = fs::read(&txt);
+        for path in [Some(bin), Some(txt), meta.clone()].into_iter().flatten() {
+            let _ = fs::remove_file(path);
+        }
+
+        // Binary formats get their echo next to them
+        assert!(meta.is_some_and(|meta| meta.to_string_lossy().ends_with(".bin.meta.json")));
+        assert_eq!(saved.unwrap(), received);
+        assert!(commented.is_none());
+        let saved_text = saved_text.unwrap();
+        assert!(saved_text.starts_with(b"# Saved by LazyCurl\n# GET http://x/file\n"));
+        assert!(saved_text.ends_with(&received));
+    }
+
+    #[test]
+    fn attachment_names_skip_malformed_parameters() {
+        let named = |disposition: &str| {
+            let response = Response {
+                headers: vec![("Content-Disposition".into(), disposition.into())],
+                ..Response::default()
+            };
+            attachment_name(&response)
+        };
+        assert_eq!(
+            named("attachment; filename*=report.pdf; filename=\"report.pdf\""),
+            Some("report.pdf".into())
+        );
+        assert_eq!(
+            named("attachment; filename=a.txt; filename*=UTF-8''%C3%A9t%C3%A9.txt"),
+            Some("été.txt".into())
+        );
+        assert_eq!(
+            named("attachment; filename=\"../../.bashrc\""),
+            Some(".bashrc".into())
+        );
+        assert_eq!(named("attachment; filename=\"dir/.\""), None);
+        assert_eq!(named("attachment; filename=.."), None);
+        assert_eq!(named("inline; filename=a.txt"), None);
+    }
+}
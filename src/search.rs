@@ -0,0 +1,116 @@
+//! Searching the response pane, for literal text or a regular expression,
+//! line by line.
+
+use std::ops::Range;
+
+use regex::{Regex, RegexBuilder};
+
+use crate::lines::IndexedText;
+
+#[derive(Debug, Clone, Default)]
+pub struct Search {
+    pub pattern: String,
+    pub regex: bool, // The pattern is a regular expression rather than text
+    pub case_sensitive: bool,
+    /// Line and byte range within it of every match, in order.
+    pub matches: Vec<(usize, Range<usize>)>,
+    pub current: usize, // Index into `matches`
+    /// Why the pattern isn't a valid regular expression.
+    pub error: Option<String>,
+}
+
+impl Search {
+    fn compile(&self) -> Result<Regex, String> {
+        let pattern = if self.regex {
+            self.pattern.clone()
+        } else {
+            regex::escape(&self.pattern)
+        };
+        RegexBuilder::new(&pattern)
+            .case_insensitive(!self.case_sensitive)
+            .build()
+            .map_err(|err| match err {
+                regex::Error::Syntax(message) => message.lines().last().unwrap_or("").to_string(),
+                err => err.to_string(),
+            })
+    }
+
+    /// Finds the matches in `text` afresh, the current one the first at or
+    /// after line `from`. Empty matches are left out.
+    pub fn run(&mut self, text: &IndexedText, from: usize) {
+        self.matches.clear();
+        self.current = 0;
+        let regex = match self.compile() {
+            Ok(regex) => regex,
+            Err(err) => {
+                self.error = Some(err);
+                return;
+            }
+        };
+        self.error = None;
+        for (line_no, line) in text.window(0, text.line_count()) {
+            self.matches.extend(
+                regex
+                    .find_iter(line)
+                    .filter(|found| !found.is_empty())
+                    .map(|found| (line_no, found.range())),
+            );
+        }
+        self.current = self
+            .matches
+            .iter()
+            .position(|(line, _)| *line >= from)
+            .unwrap_or_default();
+    }
+
+    /// Forgets the pattern and its matches, keeping how to search.
+    pub fn clear(&mut self) {
+        self.pattern.clear();
+        self.matches.clear();
+        self.current = 0;
+        self.error = None;
+    }
+
+    /// Line of the current match.
+    pub fn line(&self) -> Option<usize> {
+        self.matches.get(self.current).map(|(line, _)| *line)
+    }
+
+    /// Moves to the next match, or with `back` the previous one, wrapping
+    /// around at the ends.
+    pub fn step(&mut self, back: bool) -> Option<usize> {
+        let count = self.matches.len();
+        if count == 0 {
+            return None;
+        }
+        self.current = if back {
+            (self.current + count - 1) % count
+        } else {
+            (self.current + 1) % count
+        };
+        self.line()
+    }
+
+    /// Matches on line `line`, with whether each is the current one.
+    pub fn on_line(&self, line: usize) -> impl Iterator<Item = (Range<usize>, bool)> + '_ {
+        let start = self.matches.partition_point(|(l, _)| *l < line);
+        self.matches[start..]
+            .iter()
+            .enumerate()
+            .take_while(move |(_, (l, _))| *l == line)
+            .map(move |(i, (_, range))| (range.clone(), start + i == self.current))
+    }
+
+    /// How the search is set up, e.g. `regex, ignoring case`.
+    pub fn mode(&self) -> String {
+        format!(
+            "{}, {}",
+            if self.regex { "regex" } else { "text" },
+            if self.case_sensitive {
+                "case-sensitive"
+            } else {
+                "ignoring case"
+            }
+        )
+    }
+}
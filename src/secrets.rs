@@ -0,0 +1,55 @@
+//! Retrieval of secret values referenced as `{{secret.name}}`.
+//!
+//! Secrets are looked up in the system keyring first (service `lazycurl`,
+//! user `name`) and then in `pass` (entry `lazycurl/name`). Values are only
+//! ever returned to the caller; nothing is cached or written to disk.
+
+use std::process::Command;
+
+/// Keyring service and `pass` folder secrets are stored under.
+const SERVICE: &str = "lazycurl";
+
+/// Fetches the secret called `name`, describing every source that was tried
+/// when it can't be found.
+pub fn lookup(name: &str) -> Result<String, String> {
+    if name.is_empty() {
+        return Err("empty secret name in {{secret.}}".into());
+    }
+
+    let keyring_err = match from_keyring(name) {
+        Ok(value) => return Ok(value),
+        Err(err) => err,
+    };
+    let pass_err = match from_pass(name) {
+        Ok(value) => return Ok(value),
+        Err(err) => err,
+    };
+
+    Err(format!(
+        "could not retrieve secret '{}': keyring: {}; pass: {}",
+        name, keyring_err, pass_err
+    ))
+}
+
+fn from_keyring(name: &str) -> Result<String, String> {
+    keyring::Entry::new(SERVICE, name)
+        .and_then(|entry| entry.get_password())
+        .map_err(|e| e.to_string())
+}
+
+fn from_pass(name: &str) -> Result<String, String> {
+    let output = Command::new("pass")
+        .arg("show")
+        .arg(format!("{}/{}", SERVICE, name))
+        .output()
+        .map_err(|e| e.to_string())?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(stderr.trim().to_string());
+    }
+
+    // `pass` keeps the secret on the first line, metadata may follow
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(stdout.lines().next().unwrap_or_default().to_string())
+}
@@ -0,0 +1,81 @@
+//! Counts of the requests sent this session.
+
+use crate::request::{Request, METHODS};
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Stats {
+    pub sent: usize,
+    /// Responses by status class, 1xx first.
+    pub by_class: [usize; 5],
+    pub failed: usize, // Sent but never answered: connection errors, timeouts
+    pub by_method: [usize; METHODS.len()],
+    pub bytes_sent: usize,     // Request bodies, decoded when base64
+    pub bytes_received: usize, // Response bodies read
+}
+
+impl Stats {
+    /// Counts `request`, answered with `status` and `received` body bytes
+    /// or, with `None`, not at all.
+    pub fn record(&mut self, request: &Request, status: Option<u16>, received: usize) {
+        self.sent += 1;
+        self.by_method[request.method] += 1;
+        // A body that isn't valid base64 is never sent, nor is a GET's
+        if request.sends_body() {
+            self.bytes_sent += request.body_bytes().map_or(0, |body| body.len());
+        }
+        self.bytes_received += received;
+        match status.map(|status| status / 100) {
+            Some(class @ 1..=5) => self.by_class[class as usize - 1] += 1,
+            _ => self.failed += 1,
+        }
+    }
+
+    /// Requests answered with a 2xx or 3xx.
+    pub fn succeeded(&self) -> usize {
+        self.by_class[1] + self.by_class[2]
+    }
+
+    /// Requests answered with a 4xx or 5xx, or not at all.
+    pub fn errors(&self) -> usize {
+        self.by_class[3] + self.by_class[4] + self.failed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bodies_are_counted_as_sent() {
+        let mut stats = Stats::default();
+        let binary = Request {
+            method: 1,
+            body: "AP8=".into(),
+            body_base64: true,
+            ..Request::default()
+        };
+        stats.record(&binary, Some(200), 5);
+        stats.record(
+            &Request {
+                body: "abc".into(),
+                body_base64: false,
+                ..binary.clone()
+            },
+            None,
+            0,
+        );
+        let invalid = Request {
+            body: "not base64!".into(),
+            ..binary
+        };
+        stats.record(&invalid, Some(404), 9);
+        let get = Request {
+            body: "left over".into(),
+            ..Request::default()
+        };
+        stats.record(&get, Some(204), 0);
+        assert_eq!(stats.bytes_sent, 5);
+        assert_eq!(stats.bytes_received, 14);
+        assert_eq!((stats.sent, stats.succeeded(), stats.errors()), (4, 2, 2));
+    }
+}
@@ -0,0 +1,63 @@
+//! JSON files LazyCurl persists in its config directory.
+
+use std::{
+    fs, io,
+    path::PathBuf,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::config;
+
+/// Cleared when the directory turned out to be unwritable at startup.
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Checks that files can be written to the config directory. If they
+/// can't, saving is turned off for the session and the reason returned.
+pub fn check_writable() -> Result<(), String> {
+    let result = path(".write-test")
+        .ok_or_else(|| "no config directory".to_string())
+        .and_then(|path| {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+            }
+            fs::write(&path, b"").map_err(|e| format!("{}: {}", path.display(), e))?;
+            let _ = fs::remove_file(&path);
+            Ok(())
+        });
+    if result.is_err() {
+        ENABLED.store(false, Ordering::Relaxed);
+    }
+    result
+}
+
+fn path(name: &str) -> Option<PathBuf> {
+    config::dir().map(|dir| dir.join(name))
+}
+
+/// Reads the file called `name`; a missing file gives the default value.
+pub fn load<T: DeserializeOwned + Default>(name: &str) -> Result<T, String> {
+    let Some(path) = path(name) else {
+        return Ok(T::default());
+    };
+    match fs::read_to_string(&path) {
+        Ok(text) => serde_json::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(T::default()),
+        Err(err) => Err(format!("{}: {}", path.display(), err)),
+    }
+}
+
+/// Writes `value` to the file called `name`, creating the directory first.
+/// Does nothing once saving has been turned off, as the user was told.
+pub fn save<T: Serialize>(name: &str, value: &T) -> Result<(), String> {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return Ok(());
+    }
+    let path = path(name).ok_or("no config directory to store data in")?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    }
+    let text = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    fs::write(&path, text).map_err(|e| format!("{}: {}", path.display(), e))
+}
@@ -0,0 +1,63 @@
+//! Body chunks as they arrive from the network, kept with their arrival
+//! time so the response pane can show when each part came in.
+
+use chrono::{DateTime, Local};
+
+/// One read from the response body.
+#[derive(Debug, Clone)]
+pub struct Chunk {
+    pub at: DateTime<Local>,
+    pub text: String,
+}
+
+/// Joins `chunks` back into the body text. With `timestamps`, every
+/// non-empty line is prefixed with the arrival time of the chunk it started
+/// in.
+pub fn render(chunks: &[Chunk], timestamps: bool) -> String {
+    if !timestamps {
+        return chunks.iter().map(|chunk| chunk.text.as_str()).collect();
+    }
+
+    let mut out = String::new();
+    let mut at_line_start = true;
+    for chunk in chunks {
+        let stamp = chunk.at.format("[%H:%M:%S%.3f] ").to_string();
+        for c in chunk.text.chars() {
+            if at_line_start && c != '\n' {
+                out.push_str(&stamp);
+            }
+            out.push(c);
+            at_line_start = c == '\n';
+        }
+    }
+    out
+}
+
+/// Decodes UTF-8 across read boundaries: bytes of a character split between
+/// two reads are held back until the rest arrives.
+#[derive(Debug, Default)]
+pub struct Utf8Decoder {
+    pending: Vec<u8>,
+}
+
+impl Utf8Decoder {
+    pub fn push(&mut self, bytes: &[u8]) -> String {
+        self.pending.extend_from_slice(bytes);
+        let valid = match std::str::from_utf8(&self.pending) {
+            Ok(_) => self.pending.len(),
+            // An incomplete sequence at the end is waited on, anything else
+            // is invalid and decoded lossily right away
+            Err(err) if err.error_len().is_none() => err.valid_up_to(),
+            Err(_) => self.pending.len(),
+        };
+        let rest = self.pending.split_off(valid);
+        let text = String::from_utf8_lossy(&self.pending).into_owned();
+        self.pending = rest;
+        text
+    }
+
+    /// Whatever is still held back once the body has ended.
+    pub fn finish(&mut self) -> String {
+        String::from_utf8_lossy(&std::mem::take(&mut self.pending)).into_owned()
+    }
+}
@@ -0,0 +1,130 @@
+//! Expansion of `{{...}}` placeholders in request fields at send time.
+
+use crate::{dotenv, request::Request, secrets};
+
+/// Expands every `{{name}}` placeholder in `text` using `lookup`.
+///
+/// `lookup` receives the trimmed placeholder name and returns `Ok(None)` for
+/// names it does not handle, in which case the placeholder is kept verbatim.
+pub fn expand<F>(text: &str, mut lookup: F) -> Result<String, String>
+where
+    F: FnMut(&str) -> Result<Option<String>, String>,
+{
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        let end = start + 2 + len;
+        let name = rest[start + 2..end].trim();
+
+        out.push_str(&rest[..start]);
+        match lookup(name)? {
+            Some(value) => out.push_str(&value),
+            None => out.push_str(&rest[start..end + 2]),
+        }
+        rest = &rest[end + 2..];
+    }
+
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Resolves all placeholders LazyCurl knows about in `text`.
+///
+/// Currently this is `{{secret.name}}`, fetched through [`secrets::lookup`],
+/// and `{{NAME}}` for variables loaded from a `.env` file ([`dotenv`]).
+pub fn resolve(text: &str) -> Result<String, String> {
+    expand(text, |name| match name.strip_prefix("secret.") {
+        Some(secret) => secrets::lookup(secret).map(Some),
+        None => Ok(dotenv::get(name)),
+    })
+}
+
+/// Placeholder replaced by the OAuth2 access token.
+pub const OAUTH2_TOKEN: &str = "oauth2.token";
+
+/// Whether a field of `request` that gets sent holds `{{name}}`.
+pub fn uses(request: &Request, name: &str) -> bool {
+    let mut found = false;
+    let _ = map_fields(request, |text| {
+        expand(text, |n| {
+            found |= n == name;
+            Ok(None)
+        })
+    });
+    found
+}
+
+/// Returns a copy of `request` with `{{name}}` replaced by `value`.
+pub fn fill(request: &Request, name: &str, value: &str) -> Request {
+    map_fields(request, |text| {
+        expand(text, |n| Ok((n == name).then(|| value.to_string())))
+    })
+    .unwrap_or_else(|_| request.clone())
+}
+
+/// Labels of the `{{prompt:Label}}` placeholders in `request`, in the order
+/// they appear and each only once.
+pub fn prompt_labels(request: &Request) -> Vec<String> {
+    let mut labels = Vec::new();
+    let _ = map_fields(request, |text| {
+        expand(text, |name| {
+            if let Some(label) = name.strip_prefix("prompt:").map(str::trim) {
+                if !labels.iter().any(|l| l == label) {
+                    labels.push(label.to_string());
+                }
+            }
+            Ok(None)
+        })
+    });
+    labels
+}
+
+/// Returns a copy of `request` with `{{prompt:Label}}` placeholders
+/// replaced by the value typed for their label.
+pub fn fill_prompts(request: &Request, answers: &[(String, String)]) -> Request {
+    map_fields(request, |text| {
+        expand(text, |name| {
+            let Some(label) = name.strip_prefix("prompt:").map(str::trim) else {
+                return Ok(None);
+            };
+            Ok(answers
+                .iter()
+                .find(|(l, _)| l == label)
+                .map(|(_, value)| value.clone()))
+        })
+    })
+    .unwrap_or_else(|_| request.clone())
+}
+
+/// Applies `f` to every field of `request` that gets sent.
+fn map_fields<F>(request: &Request, mut f: F) -> Result<Request, String>
+where
+    F: FnMut(&str) -> Result<String, String>,
+{
+    let headers = request
+        .headers
+        .iter()
+        .map(|(k, v)| Ok((f(k)?, f(v)?)))
+        .collect::<Result<_, String>>()?;
+
+    Ok(Request {
+        url: f(&request.url)?,
+        headers,
+        body: f(&request.body)?,
+        cookies: f(&request.cookies)?,
+        host: f(&request.host)?,
+        sni: f(&request.sni)?,
+        ..request.clone()
+    })
+}
+
+/// Returns a copy of `request` with placeholders resolved in every field
+/// that gets sent. The original stays untouched so secrets never end up in
+/// the editable state.
+pub fn resolve_request(request: &Request) -> Result<Request, String> {
+    map_fields(request, resolve)
+}
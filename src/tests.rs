@@ -0,0 +1,547 @@
+//! Headless tests of the app: synthetic key events go through
+//! [`App::on_key`] and the result is checked on the state or on a frame
+//! drawn to an in-memory terminal.
+
+use std::{env, process};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use lazycurl::{
+    baseline::Baselines,
+    config::{self, Config},
+    history::History,
+    httpfile,
+    recording::Recordings,
+    request::METHODS,
+    template,
+};
+use ratatui::{backend::TestBackend, Terminal};
+
+use crate::{
+    app::{App, EditTarget, Focus, Poll, PromptAction, TIMEOUT_RANGE},
+    ui,
+};
+
+struct Harness {
+    app: App,
+}
+
+impl Harness {
+    fn new() -> Self {
+        Self::with_config(Config::default())
+    }
+
+    fn with_config(config: Config) -> Self {
+        // Sends go to the history file, which mustn't be the user's
+        config::set_dir(env::temp_dir().join(format!("lazycurl-tests-{}", process::id())));
+        let app = App::new(
+            config,
+            Recordings::default(),
+            History::default(),
+            Baselines::default(),
+        )
+        .unwrap();
+        Self { app }
+    }
+
+    fn press(&mut self, code: KeyCode) -> &mut Self {
+        self.app.on_key(KeyEvent::new(code, KeyModifiers::NONE));
+        self
+    }
+
+    fn ctrl(&mut self, c: char) -> &mut Self {
+        self.app
+            .on_key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL));
+        self
+    }
+
+    fn type_text(&mut self, text: &str) -> &mut Self {
+        for c in text.chars() {
+            self.press(KeyCode::Char(c));
+        }
+        self
+    }
+
+    /// The frame the app would draw on a 120x40 terminal, row by row.
+    fn screen(&self) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(120, 40)).unwrap();
+        terminal.draw(|frame| ui::draw(frame, &self.app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let mut screen = String::new();
+        for y in 0..buffer.area.height {
+            for x in 0..buffer.area.width {
+                screen.push_str(buffer[(x, y)].symbol());
+            }
+            screen.push('\n');
+        }
+        screen
+    }
+}
+
+#[test]
+fn typing_edits_the_url_and_its_params() {
+    let mut h = Harness::new();
+    h.type_text("http://localhost/items?page=2&q");
+    assert_eq!(h.app.request.url, "http://localhost/items?page=2&q");
+    assert_eq!(
+        h.app.request.params,
+        vec![("page".into(), Some("2".into())), ("q".into(), None)]
+    );
+
+    h.press(KeyCode::Backspace).press(KeyCode::Backspace);
+    assert_eq!(h.app.request.url, "http://localhost/items?page=2");
+    assert_eq!(h.app.request.params.len(), 1);
+}
+
+#[test]
+fn arrows_in_the_url_pick_the_method() {
+    let mut h = Harness::new();
+    assert_eq!(h.app.request.method_name(), "GET");
+    h.press(KeyCode::Down);
+    assert_eq!(h.app.request.method_name(), "POST");
+    h.press(KeyCode::End);
+    assert_eq!(h.app.request.method, METHODS.len() - 1);
+    h.press(KeyCode::Home);
+    assert_eq!(h.app.request.method_name(), "GET");
+}
+
+#[test]
+fn tab_cycles_focus_and_back_tab_reverses() {
+    let mut h = Harness::new();
+    let order = [
+        Focus::Headers,
+        Focus::Params,
+        Focus::Body,
+        Focus::Response,
+        Focus::Url,
+    ];
+    for focus in order {
+        h.press(KeyCode::Tab);
+        assert_eq!(h.app.focus, focus);
+    }
+    h.press(KeyCode::BackTab);
+    assert_eq!(h.app.focus, Focus::Response);
+}
+
+#[test]
+fn entering_an_editor_switches_the_options_view() {
+    let mut h = Harness::new();
+    h.press(KeyCode::Tab);
+    assert_eq!(h.app.options_mode, 0);
+    h.press(KeyCode::Tab);
+    assert_eq!(h.app.options_mode, 2);
+    h.press(KeyCode::Tab);
+    assert_eq!(h.app.options_mode, 1);
+}
+
+#[test]
+fn uppercase_letters_in_the_url_switch_the_options_view() {
+    let mut h = Harness::new();
+    for (key, mode) in [('B', 1), ('P', 2), ('C', 3), ('W', 4), ('H', 0)] {
+        h.press(KeyCode::Char(key));
+        assert_eq!(h.app.options_mode, mode);
+    }
+    assert!(h.app.request.url.is_empty());
+}
+
+#[test]
+fn header_rows_become_request_headers() {
+    let mut h = Harness::new();
+    h.press(KeyCode::Tab)
+        .type_text("Accept: text/plain")
+        .press(KeyCode::Enter)
+        .type_text("incomplete")
+        .press(KeyCode::Enter)
+        .type_text("X-Id:  7 ");
+    assert_eq!(
+        h.app.request.headers,
+        vec![
+            ("Accept".into(), "text/plain".into()),
+            ("X-Id".into(), "7".into()),
+        ]
+    );
+
+    // Leaving and coming back edits the same headers
+    h.press(KeyCode::BackTab).press(KeyCode::Tab);
+    assert_eq!(h.app.headers_editor.rows, ["Accept: text/plain", "X-Id: 7"]);
+    h.ctrl('y');
+    assert_eq!(h.app.request.headers.len(), 3);
+}
+
+#[test]
+fn param_rows_rewrite_the_url() {
+    let mut h = Harness::new();
+    h.type_text("http://localhost/search?q=a#top");
+    h.press(KeyCode::Tab).press(KeyCode::Tab);
+    assert_eq!(h.app.focus, Focus::Params);
+    h.press(KeyCode::Enter).type_text("limit=5");
+    assert_eq!(h.app.request.url, "http://localhost/search?q=a&limit=5#top");
+}
+
+#[test]
+fn body_typing_inserts_newlines_unless_enter_sends() {
+    let mut h = Harness::new();
+    h.press(KeyCode::Tab)
+        .press(KeyCode::Tab)
+        .press(KeyCode::Tab);
+    assert_eq!(h.app.focus, Focus::Body);
+    h.type_text("{").press(KeyCode::Enter).type_text("}");
+    assert_eq!(h.app.request.body, "{\n}");
+    h.press(KeyCode::Backspace);
+    assert_eq!(h.app.request.body, "{\n");
+
+    // Sending asks for the placeholder first, so nothing goes out
+    let mut h = Harness::with_config(Config {
+        body_enter_sends: true,
+        ..Config::default()
+    });
+    h.type_text("http://localhost/{{prompt:id}}");
+    h.press(KeyCode::Tab)
+        .press(KeyCode::Tab)
+        .press(KeyCode::Tab);
+    h.type_text("x").press(KeyCode::Enter);
+    assert_eq!(h.app.request.body, "x");
+    let prompt = h.app.prompt.as_ref().unwrap();
+    assert_eq!(prompt.action, PromptAction::PromptValue);
+    assert_eq!(prompt.label, "id");
+    h.press(KeyCode::Esc);
+    assert_eq!(h.app.status.as_deref(), Some("Request not sent"));
+    assert!(!h.app.is_in_flight());
+}
+
+#[test]
+fn prompts_take_keys_until_cancelled() {
+    let mut h = Harness::new();
+    h.ctrl('e');
+    let prompt = h.app.prompt.as_ref().unwrap();
+    assert_eq!(prompt.action, PromptAction::ExportHttp);
+    assert_eq!(prompt.input, "request.http");
+
+    // Typing goes to the prompt, not the URL, and Esc only closes it
+    h.type_text("x").press(KeyCode::Esc);
+    assert!(h.app.prompt.is_none());
+    assert!(h.app.request.url.is_empty());
+    assert!(!h.app.should_quit);
+
+    h.press(KeyCode::Esc);
+    assert!(h.app.should_quit);
+}
+
+#[test]
+fn ctrl_x_ctrl_e_asks_for_the_editor() {
+    let mut h = Harness::new();
+    h.ctrl('x').ctrl('e');
+    assert_eq!(h.app.edit, Some(EditTarget::Body));
+    assert!(h.app.prompt.is_none());
+
+    // Anything in between breaks the chord
+    let mut h = Harness::new();
+    h.ctrl('x').type_text("a").ctrl('e');
+    assert_eq!(h.app.edit, None);
+    assert_eq!(
+        h.app.prompt.as_ref().unwrap().action,
+        PromptAction::ExportHttp
+    );
+}
+
+#[test]
+fn popups_open_and_close() {
+    let mut h = Harness::new();
+    h.press(KeyCode::F(2));
+    assert_eq!(h.app.history_selected, None);
+    assert_eq!(h.app.status.as_deref(), Some("History is empty"));
+
+    h.press(KeyCode::Tab)
+        .press(KeyCode::Tab)
+        .press(KeyCode::Tab);
+    h.ctrl('n');
+    assert_eq!(h.app.snippet_selected, Some(0));
+    h.press(KeyCode::Down).press(KeyCode::Enter);
+    assert_eq!(h.app.snippet_selected, None);
+    assert_eq!(h.app.request.body, "[]");
+}
+
+#[test]
+fn fold_keys_set_the_depth_in_the_response() {
+    let mut h = Harness::new();
+    h.press(KeyCode::BackTab);
+    assert_eq!(h.app.focus, Focus::Response);
+    h.type_text("c");
+    assert_eq!(h.app.fold_depth, Some(1));
+    h.type_text("3");
+    assert_eq!(h.app.fold_depth, Some(3));
+    h.type_text("o");
+    assert_eq!(h.app.fold_depth, None);
+}
+
+#[test]
+fn the_frame_shows_the_request_being_edited() {
+    let mut h = Harness::new();
+    assert!(h.screen().contains("Response will appear here..."));
+
+    h.type_text("http://localhost/");
+    assert!(h.screen().contains("http://localhost/"));
+
+    h.press(KeyCode::Tab).type_text("Bad Name: x");
+    assert!(h.screen().contains("' ' is not allowed in a header name"));
+}
+
+#[test]
+fn decoding_the_query_leaves_an_unedited_url_as_typed() {
+    let mut h = Harness::new();
+    h.type_text("http://x/?q=a+b%26c&e=caf%c3%a9");
+    h.ctrl('d');
+    assert_eq!(h.app.request.url, "http://x/?q=a b%26c&e=café");
+    assert!(h.screen().contains("[query decoded]"));
+    h.ctrl('d');
+    assert_eq!(h.app.request.url, "http://x/?q=a+b%26c&e=caf%c3%a9");
+
+    // Edited while decoded, the URL is encoded again
+    h.ctrl('d').type_text("!");
+    h.ctrl('d');
+    assert_eq!(h.app.request.url, "http://x/?q=a%20b%26c&e=caf%C3%A9!");
+}
+
+#[test]
+fn the_timeout_slider_is_clamped_and_applied_on_enter() {
+    let mut h = Harness::new();
+    h.press(KeyCode::F(6));
+    assert_eq!(h.app.timeout_setting, Some(30));
+    h.press(KeyCode::Right).press(KeyCode::PageUp);
+    assert_eq!(h.app.timeout_setting, Some(41));
+    assert!(h.screen().contains("41 s"));
+
+    // Esc keeps the timeout as it was
+    h.press(KeyCode::Esc);
+    assert_eq!(h.app.config.timeout_secs, 30);
+
+    h.press(KeyCode::F(6))
+        .press(KeyCode::Home)
+        .press(KeyCode::Left);
+    assert_eq!(h.app.timeout_setting, Some(TIMEOUT_RANGE.0));
+    h.press(KeyCode::End)
+        .press(KeyCode::Right)
+        .press(KeyCode::Enter);
+    assert_eq!(h.app.timeout_setting, None);
+    assert_eq!(h.app.config.timeout_secs, TIMEOUT_RANGE.1);
+}
+
+#[test]
+fn timeouts_outside_the_slider_open_it_at_its_ends() {
+    for (configured, shown) in [(600, TIMEOUT_RANGE.1), (0, TIMEOUT_RANGE.0)] {
+        let mut h = Harness::with_config(Config {
+            timeout_secs: configured,
+            ..Config::default()
+        });
+        h.press(KeyCode::F(6));
+        assert_eq!(h.app.timeout_setting, Some(shown));
+        assert!(h.screen().contains(&format!("{} s", shown)));
+        // Cancelling leaves the configured value alone
+        h.press(KeyCode::Esc);
+        assert_eq!(h.app.config.timeout_secs, configured);
+    }
+}
+
+#[test]
+fn polling_waits_for_the_response_before_the_interval() {
+    let mut h = Harness::new();
+    h.press(KeyCode::F(8));
+    assert_eq!(h.app.polling, None);
+
+    // Replayed responses come back at once, without a server
+    h.type_text("http://localhost/status").ctrl('p');
+    h.press(KeyCode::F(8));
+    assert!(matches!(h.app.polling, Some(Poll::WaitingForInterval(_))));
+    h.app.tick();
+    assert!(h.app.status.is_some());
+    let Some(Poll::WaitingForInterval(due)) = h.app.polling else {
+        panic!("polling should wait for the interval once answered");
+    };
+    assert!(due > std::time::Instant::now());
+    assert!(h.screen().contains("[polling: next in 5 s]"));
+
+    h.press(KeyCode::F(8));
+    assert_eq!(h.app.polling, None);
+}
+
+#[test]
+fn the_expected_response_is_kept_with_the_request() {
+    let mut h = Harness::new();
+    h.type_text("http://localhost/items");
+    h.app
+        .finish_edit(EditTarget::Expected, Ok("{\n  \"items\": []\n}\n".into()));
+    assert_eq!(h.app.request.expected, "{\n  \"items\": []\n}");
+    assert!(h.app.request.body.is_empty());
+
+    let exported = httpfile::export(&h.app.request);
+    assert!(exported.starts_with("# @expected\n# {\n#   \"items\": []\n# }\nGET "));
+    assert_eq!(httpfile::import(&exported).unwrap(), h.app.request);
+}
+
+#[test]
+fn the_stats_overlay_counts_sends_until_reset() {
+    let mut h = Harness::new();
+    // Without a recording, the replayed send fails like an unanswered one
+    h.type_text("http://localhost/status").ctrl('p').ctrl('j');
+    assert_eq!(h.app.stats.sent, 1);
+    assert_eq!(h.app.stats.errors(), 1);
+
+    h.press(KeyCode::F(9));
+    assert!(h.screen().contains("Requests sent: 1"));
+    h.type_text("r");
+    assert_eq!(h.app.stats.sent, 0);
+    assert!(h.app.request.url.ends_with("/status"));
+    h.press(KeyCode::Esc);
+    assert!(!h.app.show_stats);
+    assert!(!h.app.should_quit);
+}
+
+#[test]
+fn focus_moves_to_the_response_when_configured() {
+    let mut h = Harness::new();
+    h.type_text("http://localhost/").ctrl('p').ctrl('j');
+    assert_eq!(h.app.focus, Focus::Url);
+
+    let mut h = Harness::with_config(Config {
+        focus_response: true,
+        ..Config::default()
+    });
+    h.type_text("http://localhost/").ctrl('p').ctrl('j');
+    assert_eq!(h.app.focus, Focus::Response);
+}
+
+#[test]
+fn alt_k_creates_and_switches_cookie_jars() {
+    let mut h = Harness::new();
+    h.app
+        .on_key(KeyEvent::new(KeyCode::Char('k'), KeyModifiers::ALT));
+    assert_eq!(h.app.prompt.as_ref().unwrap().input, "default");
+    for _ in 0.."default".len() {
+        h.press(KeyCode::Backspace);
+    }
+    h.type_text("work").press(KeyCode::Enter);
+    assert_eq!(h.app.jars.active(), "work");
+    assert!(h.screen().contains("[jar: work, 0 cookies]"));
+
+    h.app
+        .on_key(KeyEvent::new(KeyCode::Char('k'), KeyModifiers::ALT));
+    for _ in 0.."work".len() {
+        h.press(KeyCode::Backspace);
+    }
+    h.type_text("default").press(KeyCode::Enter);
+    assert_eq!(
+        h.app.status.as_deref(),
+        Some("Switched to cookie jar 'default'")
+    );
+    let names: Vec<String> = h.app.jars.list().into_iter().map(|jar| jar.0).collect();
+    assert_eq!(names, ["default", "work"]);
+}
+
+#[test]
+fn alt_b_switches_the_body_between_text_and_base64() {
+    let mut h = Harness::new();
+    h.app.request.url = "http://localhost/upload".into();
+    h.app.request.body = "héllo".into();
+    h.app
+        .on_key(KeyEvent::new(KeyCode::Char('b'), KeyModifiers::ALT));
+    assert_eq!(h.app.request.body, "aMOpbGxv");
+    assert!(h.app.request.body_base64);
+    assert_eq!(h.app.request.body_bytes().unwrap(), "héllo".as_bytes());
+
+    // Binary can't be shown as text, so it stays base64
+    h.app.request.body = "AP8=".into();
+    h.app
+        .on_key(KeyEvent::new(KeyCode::Char('b'), KeyModifiers::ALT));
+    assert!(h.app.request.body_base64);
+    assert_eq!(
+        h.app.status.as_deref(),
+        Some("Body kept as base64: it decodes to 2 bytes of binary")
+    );
+
+    let exported = httpfile::export(&h.app.request);
+    assert!(httpfile::import(&exported).unwrap().body_base64);
+}
+
+#[test]
+fn alt_e_loads_dotenv_variables_and_reports_bad_lines() {
+    let path = env::temp_dir().join(format!("lazycurl-tests-{}.env", process::id()));
+    std::fs::write(
+        &path,
+        "# API access\nexport DOTENV_TEST_HOST=api.example.com # prod\nDOTENV_TEST_TOKEN=\"a \\\"b\\\"\nc\"\nnot a variable\nDOTENV_TEST_RAW='$x \\n'\n",
+    )
+    .unwrap();
+    let mut h = Harness::new();
+    h.app
+        .on_key(KeyEvent::new(KeyCode::Char('e'), KeyModifiers::ALT));
+    h.app.prompt.as_mut().unwrap().input = path.display().to_string();
+    h.press(KeyCode::Enter);
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(
+        h.app.status,
+        Some(format!(
+            "Loaded 3 variables from {}, skipped line 5: expected NAME=value",
+            path.display()
+        ))
+    );
+    assert_eq!(
+        template::resolve("https://{{DOTENV_TEST_HOST}}/{{DOTENV_TEST_RAW}}|{{DOTENV_TEST_TOKEN}}"),
+        Ok("https://api.example.com/$x \\n|a \"b\"\nc".to_string())
+    );
+}
+
+#[test]
+fn response_search_finds_text_or_regex_matches_and_steps_through_them() {
+    let mut h = Harness::new();
+    h.app.focus = Focus::Response;
+    h.app.response_text = "{\n  \"id\": 7,\n  \"ID\": 42,\n  \"name\": \"id-7\"\n}"
+        .to_string()
+        .into();
+    h.press(KeyCode::Char('/'))
+        .type_text("id")
+        .press(KeyCode::Enter);
+    assert_eq!(h.app.status.as_deref(), Some("Match 1 of 3"));
+    h.press(KeyCode::Char('N'));
+    assert_eq!(h.app.response_scroll, 3);
+    assert!(h.screen().contains("[/id/: 3 of 3]"));
+
+    h.press(KeyCode::Char('i'));
+    assert_eq!(h.app.search.matches.len(), 2);
+    h.press(KeyCode::Char('r')).press(KeyCode::Char('/'));
+    h.app.prompt.as_mut().unwrap().input = r"\d+".into();
+    h.press(KeyCode::Enter);
+    assert_eq!(h.app.search.matches.len(), 3);
+    // The prompt starts from the last pattern
+    h.press(KeyCode::Char('/'))
+        .type_text("(")
+        .press(KeyCode::Enter);
+    assert!(h
+        .app
+        .status
+        .as_deref()
+        .is_some_and(|status| status.starts_with("Invalid regex /\\d+(/: ")));
+    assert!(h.screen().contains("[search: invalid regex]"));
+}
+
+#[test]
+fn the_clear_response_key_empties_only_the_response_pane() {
+    let config: Config = toml::from_str("clear_response_key = \"ctrl+alt+l\"").unwrap();
+    assert!(toml::from_str::<Config>("clear_response_key = \"l\"").is_err());
+    let mut h = Harness::with_config(config);
+    h.type_text("http://localhost/items");
+    h.app.focus = Focus::Response;
+    h.app.response_text = "one\ntwo\nthree".to_string().into();
+    h.press(KeyCode::Char('/'))
+        .type_text("t")
+        .press(KeyCode::Enter);
+    assert_eq!(h.app.response_scroll, 1);
+    assert_eq!(h.app.config.clear_response_key.to_string(), "Ctrl+Alt+L");
+
+    h.app.on_key(KeyEvent::new(
+        KeyCode::Char('l'),
+        KeyModifiers::CONTROL | KeyModifiers::ALT,
+    ));
+    assert_eq!(h.app.response_text.as_str(), "Response will appear here...");
+    assert_eq!(h.app.response_scroll, 0);
+    assert!(h.app.search.pattern.is_empty() && h.app.search.matches.is_empty());
+    assert_eq!(h.app.request.url, "http://localhost/items");
+}
@@ -0,0 +1,144 @@
+//! Capture of what a verbose client writes to and reads from its
+//! connections, for showing one request curl -v style. reqwest logs the
+//! bytes of connections made with `connection_verbose` at trace level;
+//! while a capture runs, those records are kept instead of dropped. The
+//! program installs [`Capture`] as its logger to make that happen.
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Mutex,
+};
+
+use log::{Level, Log, Metadata, Record};
+
+/// Where reqwest logs the bytes of verbose connections.
+const TARGET: &str = "reqwest::connect::verbose";
+/// Only enabled by [`Capture`], to tell whether it's the installed logger.
+const PROBE: &str = "lazycurl::trace::probe";
+
+static CAPTURING: AtomicBool = AtomicBool::new(false);
+/// Each write or read, with whether it was a write.
+static RECORDS: Mutex<Vec<(bool, Vec<u8>)>> = Mutex::new(Vec::new());
+
+/// The logger keeping reqwest's records while a capture runs, to be
+/// installed with [`log::set_logger`] and a max level of `Trace`.
+pub struct Capture;
+
+impl Log for Capture {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.target() == PROBE
+            || CAPTURING.load(Ordering::Relaxed) && metadata.target() == TARGET
+    }
+
+    fn log(&self, record: &Record) {
+        if !CAPTURING.load(Ordering::Relaxed) || record.target() != TARGET {
+            return;
+        }
+        // `0123abcd write: b"GET / HTTP/1.1\r\n..."`, vectored writes being
+        // several `b"..."` in a row
+        let message = record.args().to_string();
+        let Some((head, escaped)) = message.split_once(": ") else {
+            return;
+        };
+        let written = head.contains("write");
+        if let Ok(mut records) = RECORDS.lock() {
+            records.push((written, unescape(escaped)));
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Starts keeping the bytes of verbose connections, dropping any kept
+/// before. Fails unless [`Capture`] is the installed logger.
+pub fn start() -> Result<(), String> {
+    let probe = Metadata::builder()
+        .target(PROBE)
+        .level(Level::Trace)
+        .build();
+    if log::max_level() < Level::Trace || !log::logger().enabled(&probe) {
+        return Err("the trace logger isn't installed".into());
+    }
+    if let Ok(mut records) = RECORDS.lock() {
+        records.clear();
+    }
+    CAPTURING.store(true, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Stops the capture and renders what was sent as `> ` lines and what was
+/// received as `< ` lines. Bodies are only counted.
+pub fn finish() -> Vec<String> {
+    CAPTURING.store(false, Ordering::Relaxed);
+    let records = RECORDS
+        .lock()
+        .map(|mut records| std::mem::take(&mut *records))
+        .unwrap_or_default();
+
+    // Consecutive writes make up a request, the reads after it its response
+    let mut runs: Vec<(bool, Vec<u8>)> = Vec::new();
+    for (written, bytes) in records {
+        match runs.last_mut() {
+            Some((last, run)) if *last == written => run.extend(bytes),
+            _ => runs.push((written, bytes)),
+        }
+    }
+
+    let mut lines = Vec::new();
+    for (written, bytes) in runs {
+        let marker = if written { '>' } else { '<' };
+        let mut rest = bytes.as_slice();
+        // An interim response, such as `100 Continue`, comes before the
+        // final one
+        while let Some(end) = rest.windows(4).position(|window| window == b"\r\n\r\n") {
+            let head = String::from_utf8_lossy(&rest[..end]);
+            for line in head.split("\r\n") {
+                lines.push(format!("{} {}", marker, line));
+            }
+            lines.push(marker.to_string());
+            rest = &rest[end + 4..];
+            if written || !is_interim(&head) {
+                break;
+            }
+        }
+        if !rest.is_empty() {
+            lines.push(format!("{} [{} bytes of body]", marker, rest.len()));
+        }
+    }
+    lines
+}
+
+/// Whether a response head has a 1xx status.
+fn is_interim(head: &str) -> bool {
+    head.split(' ')
+        .nth(1)
+        .is_some_and(|status| status.len() == 3 && status.starts_with('1'))
+}
+
+/// Turns reqwest's escaped `b"..."` strings back into bytes.
+fn unescape(escaped: &str) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    let mut inside = false;
+    let mut chars = escaped.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            // Quotes inside are escaped, so these start and end a string
+            '"' => inside = !inside,
+            _ if !inside => {}
+            '\\' => match chars.next() {
+                Some('n') => bytes.push(b'\n'),
+                Some('r') => bytes.push(b'\r'),
+                Some('t') => bytes.push(b'\t'),
+                Some('0') => bytes.push(0),
+                Some('x') => {
+                    let hex: String = chars.by_ref().take(2).collect();
+                    bytes.extend(u8::from_str_radix(&hex, 16).ok());
+                }
+                Some(c) => bytes.push(c as u8),
+                None => {}
+            },
+            c => bytes.push(c as u8),
+        }
+    }
+    bytes
+}
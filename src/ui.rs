@@ -0,0 +1,956 @@
+//! Rendering of the application state.
+
+use std::{ops::Range, path::PathBuf, time::Instant};
+
+use chrono_humanize::HumanTime;
+use ratatui::{
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span, Text},
+    widgets::{
+        Block, Borders, Cell, Clear, Gauge, List, ListItem, ListState, Paragraph, Row, Table, Wrap,
+    },
+    Frame,
+};
+
+use lazycurl::{
+    batch::Batch, compare::Comparison, graphql::Kind, http, query, request::METHODS, stats::Stats,
+};
+
+use crate::{
+    app::{App, Focus, Poll, Reuse, TIMEOUT_RANGE},
+    rows::RowEditor,
+};
+
+pub fn draw(frame: &mut Frame, app: &App) {
+    let size = frame.area();
+
+    // Split the UI into left (methods) and right (rest of UI)
+    let main_layout = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage(10), // Left panel for HTTP methods
+            Constraint::Percentage(90), // Right panel for input, response, etc.
+        ])
+        .split(size);
+
+    // Further split the right panel into vertical sections
+    let right_layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage(10), // Header
+            Constraint::Percentage(10), // URL Input
+            Constraint::Percentage(40), // Additional UI (Future feature)
+            Constraint::Percentage(40), // Response box
+            Constraint::Length(1),      // Status bar
+        ])
+        .split(main_layout[1]);
+
+    // Header
+    let mut header_spans = vec![Span::styled(
+        "LazyCurl - HTTP Requester",
+        Style::default()
+            .fg(Color::Cyan)
+            .add_modifier(Modifier::BOLD),
+    )];
+    if app.recording {
+        header_spans.push(Span::styled("  ● REC", Style::default().fg(Color::Red)));
+    }
+    if app.replaying {
+        header_spans.push(Span::styled(
+            "  ▶ REPLAY (offline)",
+            Style::default().fg(Color::Magenta),
+        ));
+    }
+    if app.modified(|r| r) {
+        header_spans.push(Span::styled(
+            "  * modified",
+            Style::default().fg(Color::Yellow),
+        ));
+    }
+    let header = Paragraph::new(Line::from(header_spans))
+        .block(Block::default().borders(Borders::ALL))
+        .alignment(Alignment::Center);
+
+    // URL Input Box
+    let mut input_title = if app.decoded_query.is_some() {
+        "Enter URL [query decoded]".to_string()
+    } else {
+        "Enter URL".to_string()
+    };
+    input_title.push_str(mark(app.modified(|r| &r.url)));
+    let input_box = Paragraph::new(app.request.url.clone())
+        .block(
+            Block::default()
+                .title(input_title)
+                .borders(Borders::ALL)
+                .border_style(focus_style(app, Focus::Url)),
+        )
+        .alignment(Alignment::Center);
+
+    // Method Selector List
+    let methods_items: Vec<ListItem> = METHODS
+        .iter()
+        .enumerate()
+        .map(|(i, &method)| {
+            let style = if i == app.request.method {
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default()
+            };
+            ListItem::new(Span::styled(method, style))
+        })
+        .collect();
+
+    let method_title = format!("HTTP Method{}", mark(app.modified(|r| &r.method)));
+    let method_box =
+        List::new(methods_items).block(Block::default().title(method_title).borders(Borders::ALL));
+
+    let options_text: Text = if app.focus == Focus::Headers {
+        rows_text(&app.headers_editor, |row| {
+            let (name, value) = row.split_once(':')?;
+            http::header_error(name.trim(), value.trim())
+        })
+    } else if app.focus == Focus::Params {
+        rows_text(&app.params_editor, |_| None)
+    } else if app.options_mode == 0 {
+        format!("Headers: {:?}", app.request.headers).into()
+    } else if app.focus == Focus::Body {
+        let (before, after) = app.request.body.split_at(app.body_cursor());
+        format!("{}_{}", before, after).into()
+    } else if app.options_mode == 1 {
+        let encoding = if app.request.body_base64 {
+            " (base64, sent decoded)"
+        } else {
+            ""
+        };
+        format!("Body{}: {}", encoding, app.request.body).into()
+    } else if app.options_mode == 2 {
+        params_text(app).into()
+    } else if app.options_mode == 4 {
+        effective_headers_text(app)
+    } else {
+        cookies_text(app).into()
+    };
+    let options_title = if app.focus == Focus::Body {
+        format!(
+            "Body (editing{}){}",
+            if app.request.body_base64 {
+                ", base64: sent decoded, Alt+B for text"
+            } else {
+                ""
+            },
+            mark(app.modified(|r| &r.body))
+        )
+    } else if app.focus == Focus::Headers {
+        format!(
+            "Headers (editing, one Name: value per row){}",
+            mark(app.modified(|r| &r.headers))
+        )
+    } else if app.focus == Focus::Params {
+        format!(
+            "Params (editing, one name=value per row){}",
+            mark(app.modified(|r| &r.params))
+        )
+    } else {
+        format!(
+            "Options (H: Headers{}, W: On the wire, B: Body{}, P: Params{}, C: Cookies{}) [jar: {}, {} cookies]",
+            mark(app.modified(|r| &r.headers)),
+            mark(app.modified(|r| &r.body)),
+            mark(app.modified(|r| &r.params)),
+            mark(app.modified(|r| &r.cookies)),
+            app.jars.active(),
+            app.jars.count(),
+        )
+    };
+    let options_box = Paragraph::new(options_text).block(
+        Block::default()
+            .title(options_title)
+            .borders(Borders::ALL)
+            .border_style(match app.focus {
+                Focus::Headers | Focus::Params | Focus::Body => focus_style(app, app.focus),
+                Focus::Url | Focus::Response => Style::default(),
+            }),
+    );
+
+    // Response Box
+    let mut response_title = match &app.response {
+        Some(response) => format!(
+            "Response {} {} ({} ms)",
+            response.status,
+            response.reason(),
+            response.elapsed.as_millis()
+        ),
+        None => match app.progress() {
+            Some((elapsed, 0)) => format!("Response (waiting: {:.1} s)", elapsed.as_secs_f64()),
+            Some((elapsed, bytes)) => format!(
+                "Response (receiving: {:.1} s, {} bytes so far)",
+                elapsed.as_secs_f64(),
+                bytes
+            ),
+            None => "Response".to_string(),
+        },
+    };
+    if app.show_timestamps {
+        response_title.push_str(" [timestamps]");
+    }
+    match app.polling {
+        Some(Poll::WaitingForResponse) => {
+            response_title.push_str(" [polling: waiting for response]")
+        }
+        Some(Poll::WaitingForInterval(due)) => response_title.push_str(&format!(
+            " [polling: next in {} s]",
+            due.saturating_duration_since(Instant::now())
+                .as_secs_f32()
+                .ceil()
+        )),
+        None => {}
+    }
+    if app.response.as_ref().is_some_and(|r| r.is_problem()) {
+        response_title.push_str(" [problem details]");
+    }
+    if app.trace.is_some() {
+        response_title.push_str(" [verbose send]");
+    }
+    if let Some(callback) = &app.jsonp_callback {
+        response_title.push_str(&format!(" [JSONP unwrapped: {}(...)]", callback));
+    }
+    if let Some(bytes) = app.decoded_base64 {
+        response_title.push_str(&format!(" [base64 decoded: {} bytes]", bytes));
+    }
+    if app.sort_keys {
+        response_title.push_str(" [keys sorted]");
+    }
+    if let Some(depth) = app.fold_depth {
+        response_title.push_str(&format!(" [folded below depth {}]", depth));
+    }
+    if app.response.as_ref().is_some_and(|r| r.auth_retried) {
+        response_title.push_str(" [401: token refreshed, retried]");
+    }
+    if app.response.as_ref().is_some_and(|r| r.replayed) {
+        response_title.push_str(" [replayed, offline]");
+    }
+    if let Some(note) = app.response.as_ref().and_then(|r| r.encoding_note.as_ref()) {
+        response_title.push_str(&format!(" [{}]", note));
+    }
+    match app.reuse {
+        Some(Reuse::Reused) => response_title.push_str(" [connection reused]"),
+        Some(Reuse::New) => response_title.push_str(" [new connection]"),
+        Some(Reuse::NotReused) => {
+            response_title.push_str(" [new connection: the kept-alive one was not reused]")
+        }
+        None => {}
+    }
+    let reference = if app.diffed_expected {
+        "expected"
+    } else {
+        "baseline"
+    };
+    match app.baseline_changes {
+        Some((0, 0)) => response_title.push_str(&format!(" [matches {}]", reference)),
+        Some((added, removed)) => response_title.push_str(&format!(
+            " [differs from {}: +{} -{}, v: {}]",
+            reference,
+            added,
+            removed,
+            if app.show_baseline_diff {
+                "show response"
+            } else {
+                "show diff"
+            }
+        )),
+        None => {}
+    }
+    if let Some(summary) = app.response_summary() {
+        response_title.push_str(&format!(" [{}]", summary));
+    }
+    let search = &app.search;
+    match (&search.error, search.matches.len()) {
+        _ if search.pattern.is_empty() => {}
+        (Some(_), _) => response_title.push_str(" [search: invalid regex]"),
+        (None, 0) => response_title.push_str(&format!(" [/{}/: no matches]", search.pattern)),
+        (None, count) => response_title.push_str(&format!(
+            " [/{}/: {} of {}]",
+            search.pattern,
+            search.current + 1,
+            count
+        )),
+    }
+    // Only the lines that fit are styled, however long the response is
+    let visible = right_layout[3].height.saturating_sub(2) as usize;
+    let response_lines: Vec<Line> = app
+        .response_text
+        .window(app.response_scroll, visible)
+        .map(|(i, line)| {
+            if app.highlight_line == Some(i) {
+                Line::styled(
+                    line,
+                    Style::default()
+                        .fg(Color::Red)
+                        .add_modifier(Modifier::BOLD | Modifier::REVERSED),
+                )
+            } else {
+                let style = app
+                    .diff_marks
+                    .get(i)
+                    .map_or(Style::default(), |m| diff_style(*m));
+                search_line(line, style, app.search.on_line(i))
+            }
+        })
+        .collect();
+    let mut response_block = Block::default()
+        .title(response_title)
+        .borders(Borders::ALL)
+        .border_style(focus_style(app, Focus::Response));
+    if app.response.as_ref().is_some_and(|r| r.is_problem()) {
+        response_block = response_block.title_style(Style::default().fg(Color::Red));
+    }
+
+    // Render UI Components
+    frame.render_widget(method_box, main_layout[0]); // Left panel (Method selector)
+    frame.render_widget(header, right_layout[0]); // Header (Right panel)
+    frame.render_widget(input_box, right_layout[1]); // Input field (Right panel)
+    frame.render_widget(options_box, right_layout[2]); // Input field (Right panel)
+    match (&app.batch, &app.comparison) {
+        (Some(batch), _) => frame.render_widget(batch_table(batch), right_layout[3]),
+        (None, Some(comparison)) => {
+            draw_comparison(frame, comparison, app.response_scroll, right_layout[3])
+        }
+        (None, None) => match &app.response_table {
+            Some(rows) => frame.render_widget(
+                response_table(rows, app.response_scroll).block(response_block),
+                right_layout[3],
+            ),
+            // Response box (Right panel)
+            None => frame.render_widget(
+                Paragraph::new(response_lines).block(response_block),
+                right_layout[3],
+            ),
+        },
+    }
+    // Status bar (Right panel), with the last response badge on its right
+    let badge = last_sent_badge(app);
+    let status_layout = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Min(0),
+            Constraint::Length(badge.as_ref().map_or(0, |b| b.width() as u16)),
+        ])
+        .split(right_layout[4]);
+    frame.render_widget(status_bar(app), status_layout[0]);
+    if let Some(badge) = badge {
+        frame.render_widget(Paragraph::new(badge), status_layout[1]);
+    }
+
+    if let Some(selected) = app.history_selected {
+        draw_history(frame, app, selected);
+    }
+    if let Some(selected) = app.snippet_selected {
+        draw_snippets(frame, app, selected);
+    }
+    if let Some(selected) = app.schema_selected {
+        draw_schema(frame, app, selected);
+    }
+    if let Some(timeout) = app.timeout_setting {
+        draw_settings(frame, timeout, &app.config_files);
+    }
+    if app.show_stats {
+        draw_stats(frame, &app.stats);
+    }
+}
+
+/// A `percent_x` by `percent_y` rectangle centered in `area`.
+fn centered(area: Rect, percent_x: u16, percent_y: u16) -> Rect {
+    let vertical = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - percent_y) / 2),
+            Constraint::Percentage(percent_y),
+            Constraint::Percentage((100 - percent_y) / 2),
+        ])
+        .split(area);
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ])
+        .split(vertical[1])[1]
+}
+
+/// Popup listing past requests, newest first.
+fn draw_history(frame: &mut Frame, app: &App, selected: usize) {
+    let items: Vec<ListItem> = app
+        .history
+        .entries
+        .iter()
+        .map(|entry| {
+            let (status, style) = match entry.status {
+                Some(status) => (status.to_string(), status_style(status)),
+                None => ("failed".to_string(), Style::default().fg(Color::Red)),
+            };
+            let sent_at = if app.preferences.relative_history_times {
+                format!("{:<20}", HumanTime::from(entry.sent_at).to_string())
+            } else {
+                entry.sent_at.format("%Y-%m-%d %H:%M:%S  ").to_string()
+            };
+            ListItem::new(Line::from(vec![
+                Span::styled(sent_at, Style::default().fg(Color::DarkGray)),
+                Span::styled(
+                    format!("{:<7}", entry.request.method_name()),
+                    Style::default().fg(Color::Cyan),
+                ),
+                Span::raw(format!("{}  ", entry.request.url)),
+                Span::styled(status, style),
+            ]))
+        })
+        .collect();
+
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .title("History (Enter: load for editing, t: relative/absolute times, Esc: close)")
+                .borders(Borders::ALL),
+        )
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    let mut state = ListState::default().with_selected(Some(selected));
+
+    let area = centered(frame.area(), 80, 60);
+    frame.render_widget(Clear, area);
+    frame.render_stateful_widget(list, area, &mut state);
+}
+
+/// Every header the request goes out with, the ones added automatically
+/// dimmed and tagged.
+fn effective_headers_text(app: &App) -> Text<'static> {
+    let jar = app.jar_cookies();
+    let mut lines: Vec<Line> = http::effective_headers(&app.config, &app.request, jar.as_deref())
+        .into_iter()
+        .map(|(name, value, auto)| {
+            if auto {
+                Line::styled(
+                    format!("{}: {}  (auto)", name, value),
+                    Style::default().fg(Color::DarkGray),
+                )
+            } else {
+                Line::raw(format!("{}: {}", name, value))
+            }
+        })
+        .collect();
+    if !app.request.sni.is_empty() {
+        lines.push(Line::styled(
+            format!(
+                "TLS SNI: {}  (connecting to the URL's host)",
+                app.request.sni
+            ),
+            Style::default().fg(Color::Yellow),
+        ));
+    }
+    Text::from(lines)
+}
+
+/// Rows being edited, the selected one highlighted with the cursor at its
+/// end. Rows `error` finds a problem with are red, the problem spelled out
+/// under the selected one.
+fn rows_text(editor: &RowEditor, error: impl Fn(&str) -> Option<String>) -> Text<'_> {
+    let mut lines = Vec::new();
+    for (i, row) in editor.rows.iter().enumerate() {
+        let problem = error(row);
+        let style = match problem {
+            Some(_) => Style::default().fg(Color::Red),
+            None => Style::default(),
+        };
+        if i != editor.selected {
+            lines.push(Line::styled(row.as_str(), style));
+            continue;
+        }
+        lines.push(Line::styled(
+            format!("{}_", row),
+            style.add_modifier(Modifier::REVERSED),
+        ));
+        if let Some(problem) = problem {
+            lines.push(Line::styled(
+                format!("  ^ {}", problem),
+                Style::default().fg(Color::Red),
+            ));
+        }
+    }
+    Text::from(lines)
+}
+
+/// The raw query string followed by its params, one per line.
+fn params_text(app: &App) -> String {
+    let query = query::query_and_fragment(&app.request.url)
+        .0
+        .unwrap_or_default();
+    let mut text = format!("Query: {}\n", query);
+    for (name, value) in &app.request.params {
+        match value {
+            Some(value) => text.push_str(&format!("  {} = {}\n", name, value)),
+            None => text.push_str(&format!("  {} (no value)\n", name)),
+        }
+    }
+    text
+}
+
+/// Popup listing the configured body snippets.
+fn draw_snippets(frame: &mut Frame, app: &App, selected: usize) {
+    let items: Vec<ListItem> = app
+        .config
+        .snippets
+        .iter()
+        .map(|snippet| {
+            ListItem::new(Line::from(vec![
+                Span::styled(
+                    format!("{:<20}", snippet.name),
+                    Style::default().fg(Color::Cyan),
+                ),
+                Span::styled(
+                    snippet.text.replace('\n', " "),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ]))
+        })
+        .collect();
+
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .title("Snippets (Enter: insert, Esc: close)")
+                .borders(Borders::ALL),
+        )
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    let mut state = ListState::default().with_selected(Some(selected));
+
+    let area = centered(frame.area(), 60, 40);
+    frame.render_widget(Clear, area);
+    frame.render_stateful_widget(list, area, &mut state);
+}
+
+/// Popup listing the GraphQL schema's queries, mutations and types.
+fn draw_schema(frame: &mut Frame, app: &App, selected: usize) {
+    let items: Vec<ListItem> = app
+        .schema
+        .iter()
+        .map(|entry| {
+            let (kind, color) = match entry.kind {
+                Kind::Query => ("query", Color::Green),
+                Kind::Mutation => ("mutation", Color::Yellow),
+                Kind::Type => ("type", Color::Cyan),
+            };
+            ListItem::new(Line::from(vec![
+                Span::styled(format!("{:<9}", kind), Style::default().fg(color)),
+                Span::raw(entry.name.as_str()),
+                Span::styled(entry.detail.as_str(), Style::default().fg(Color::DarkGray)),
+            ]))
+        })
+        .collect();
+
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .title("GraphQL schema (Enter: scaffold request, Esc: close)")
+                .borders(Borders::ALL),
+        )
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    let mut state = ListState::default().with_selected(Some(selected));
+
+    let area = centered(frame.area(), 80, 60);
+    frame.render_widget(Clear, area);
+    frame.render_stateful_widget(list, area, &mut state);
+}
+
+/// Popup with the request timeout as a slider.
+fn draw_settings(frame: &mut Frame, timeout: u64, files: &[PathBuf]) {
+    let area = centered(frame.area(), 50, 30);
+    let block = Block::default()
+        .title("Settings (Left/Right/PgUp/PgDn, Enter: apply, Esc: cancel)")
+        .borders(Borders::ALL);
+    let inner = block.inner(area);
+    frame.render_widget(Clear, area);
+    frame.render_widget(block, area);
+
+    let rows = Layout::default()
+        .constraints([
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Min(0),
+        ])
+        .split(inner);
+    let (min, max) = TIMEOUT_RANGE;
+    frame.render_widget(
+        Paragraph::new(format!("Request timeout ({}-{} s)", min, max)),
+        rows[0],
+    );
+    let gauge = Gauge::default()
+        .gauge_style(Style::default().fg(Color::Cyan))
+        .ratio((timeout.saturating_sub(min) as f64 / (max - min) as f64).clamp(0.0, 1.0))
+        .label(format!("{} s", timeout));
+    frame.render_widget(gauge, rows[1]);
+
+    let loaded: Vec<Line> = if files.is_empty() {
+        vec![Line::raw("  none, using the defaults")]
+    } else {
+        files
+            .iter()
+            .map(|file| Line::raw(format!("  {}", file.display())))
+            .collect()
+    };
+    frame.render_widget(
+        Paragraph::new(
+            [
+                vec![Line::raw("Config files, later ones override:")],
+                loaded,
+            ]
+            .concat(),
+        )
+        .wrap(Wrap { trim: false }),
+        rows[3],
+    );
+}
+
+/// Popup with what was sent this session and how it went.
+fn draw_stats(frame: &mut Frame, stats: &Stats) {
+    let classes: Vec<String> = stats
+        .by_class
+        .iter()
+        .enumerate()
+        .map(|(i, count)| format!("{}xx: {}", i + 1, count))
+        .chain([format!("no response: {}", stats.failed)])
+        .collect();
+    let methods: Vec<String> = METHODS
+        .iter()
+        .zip(stats.by_method)
+        .map(|(method, count)| format!("{}: {}", method, count))
+        .collect();
+    let text = vec![
+        Line::raw(format!("Requests sent: {}", stats.sent)),
+        Line::from(vec![
+            Span::styled(
+                format!("Succeeded: {}", stats.succeeded()),
+                Style::default().fg(Color::Green),
+            ),
+            Span::raw("  "),
+            Span::styled(
+                format!("Errors: {}", stats.errors()),
+                Style::default().fg(Color::Red),
+            ),
+        ]),
+        Line::raw(""),
+        Line::raw(format!("By status: {}", classes.join(", "))),
+        Line::raw(format!("By method: {}", methods.join(", "))),
+        Line::raw(""),
+        Line::raw(format!(
+            "Body bytes sent: {}, received: {}",
+            stats.bytes_sent, stats.bytes_received
+        )),
+    ];
+
+    let area = centered(frame.area(), 70, 30);
+    frame.render_widget(Clear, area);
+    frame.render_widget(
+        Paragraph::new(text).wrap(Wrap { trim: false }).block(
+            Block::default()
+                .title("Session stats (r: reset, Esc: close)")
+                .borders(Borders::ALL),
+        ),
+        area,
+    );
+}
+
+/// Suffix flagging a field with unsaved changes.
+fn mark(modified: bool) -> &'static str {
+    if modified {
+        " *"
+    } else {
+        ""
+    }
+}
+
+/// The active jar's cookies for the current URL and the per-request
+/// override, making clear which of the two will be sent, then every jar
+/// with its cookie count.
+fn cookies_text(app: &App) -> String {
+    let jar = app.jar_cookies().unwrap_or_else(|| "(none)".into());
+    let sent = if app.request.cookies.is_empty() {
+        format!(
+            "Jar cookies (sent): {}\nOverride: (none, Ctrl+K to set)",
+            jar
+        )
+    } else {
+        format!(
+            "Jar cookies (bypassed): {}\nOverride (sent): {}",
+            jar, app.request.cookies
+        )
+    };
+    let jars: Vec<String> = app
+        .jars
+        .list()
+        .into_iter()
+        .map(|(name, count, active)| {
+            let marker = if active { "*" } else { "" };
+            format!("{}{} ({})", marker, name, count)
+        })
+        .collect();
+    format!("{}\nJars (Alt+K to switch): {}", sent, jars.join(", "))
+}
+
+/// Border style marking the block that has focus.
+fn focus_style(app: &App, focus: Focus) -> Style {
+    if app.focus == focus {
+        Style::default().fg(Color::Yellow)
+    } else {
+        Style::default()
+    }
+}
+
+/// A's and B's responses next to each other, differing lines colored.
+fn draw_comparison(frame: &mut Frame, comparison: &Comparison, scroll: usize, area: Rect) {
+    let halves = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(area);
+    let changed = comparison.changed_fields();
+    let visible = area.height.saturating_sub(2) as usize;
+
+    for (side, name) in ["A", "B"].iter().enumerate() {
+        let mut title = match (&comparison.b, &comparison.results[side]) {
+            (None, _) if side == 0 => "A: copied, F4: send A and B".to_string(),
+            (None, _) => "B: the request as edited".to_string(),
+            (Some(_), None) => format!("{}: sending...", name),
+            (Some(_), Some(Ok(response))) => format!(
+                "{}: {} {} ({} ms)",
+                name,
+                response.status,
+                response.reason(),
+                response.elapsed.as_millis()
+            ),
+            (Some(_), Some(Err(_))) => format!("{}: failed", name),
+        };
+        if side == 1 && comparison.b.is_some() {
+            title.push_str(&if changed.is_empty() {
+                " [same request as A]".to_string()
+            } else {
+                format!(" [changed: {}]", changed.join(", "))
+            });
+        }
+        let lines: Vec<Line> = match &comparison.columns {
+            Some(columns) => columns[side]
+                .iter()
+                .skip(scroll)
+                .take(visible)
+                .map(|(mark, line)| Line::styled(line.as_str(), diff_style(*mark)))
+                .collect(),
+            None => Vec::new(),
+        };
+        let block = Block::default()
+            .title(title)
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Yellow));
+        frame.render_widget(Paragraph::new(lines).block(block), halves[side]);
+    }
+}
+
+/// `line` in `style`, its search matches picked out, the current one most.
+fn search_line(
+    line: &str,
+    style: Style,
+    matches: impl Iterator<Item = (Range<usize>, bool)>,
+) -> Line<'_> {
+    let mut spans = Vec::new();
+    let mut end = 0;
+    for (range, current) in matches {
+        spans.push(Span::styled(&line[end..range.start], style));
+        let mut found = Style::default().fg(Color::Black).bg(Color::Yellow);
+        if current {
+            found = found.bg(Color::LightRed).add_modifier(Modifier::BOLD);
+        }
+        spans.push(Span::styled(&line[range.clone()], found));
+        end = range.end;
+    }
+    if end == 0 {
+        return Line::styled(line, style);
+    }
+    spans.push(Span::styled(&line[end..], style));
+    Line::from(spans)
+}
+
+/// Green for lines only in the newer text, red for lines only in the older.
+fn diff_style(mark: char) -> Style {
+    match mark {
+        '+' => Style::default().fg(Color::Green),
+        '-' => Style::default().fg(Color::Red),
+        _ => Style::default(),
+    }
+}
+
+/// Results of a batch run, one row per URL in file order.
+fn batch_table(batch: &Batch) -> Table<'_> {
+    let rows = batch.urls.iter().zip(&batch.results).map(|(url, result)| {
+        let (status, time, style) = match result {
+            None => (
+                "...".to_string(),
+                String::new(),
+                Style::default().fg(Color::DarkGray),
+            ),
+            Some(Ok((status, elapsed, _))) => (
+                status.to_string(),
+                format!("{} ms", elapsed.as_millis()),
+                status_style(*status),
+            ),
+            Some(Err(err)) => (err.clone(), String::new(), Style::default().fg(Color::Red)),
+        };
+        Row::new(vec![
+            Cell::from(url.as_str()),
+            Cell::from(status).style(style),
+            Cell::from(time),
+        ])
+    });
+
+    let state = if batch.cancelled {
+        "cancelled".to_string()
+    } else if batch.finished {
+        "done".to_string()
+    } else {
+        format!(
+            "running, {}/{} in flight, Esc: cancel",
+            batch.in_flight(),
+            batch.concurrency
+        )
+    };
+    let title = format!(
+        "Batch {}/{} ({})",
+        batch.completed(),
+        batch.urls.len(),
+        state
+    );
+
+    Table::new(
+        rows,
+        [
+            Constraint::Percentage(60),
+            Constraint::Percentage(25),
+            Constraint::Percentage(15),
+        ],
+    )
+    .header(
+        Row::new(vec!["URL", "Status", "Time"])
+            .style(Style::default().add_modifier(Modifier::BOLD)),
+    )
+    .block(Block::default().title(title).borders(Borders::ALL))
+}
+
+/// Widest a CSV column gets before its cells are cut off.
+const TABLE_COLUMN_MAX: usize = 40;
+
+/// CSV/TSV rows as a table with the first row as its header, scrolled so
+/// `scroll` data rows are hidden.
+fn response_table(rows: &[Vec<String>], scroll: usize) -> Table<'_> {
+    let columns = rows.iter().map(Vec::len).max().unwrap_or_default();
+    let widths: Vec<Constraint> = (0..columns)
+        .map(|column| {
+            let width = rows
+                .iter()
+                .filter_map(|row| row.get(column))
+                .map(|cell| cell.chars().count())
+                .max()
+                .unwrap_or_default();
+            Constraint::Length(width.min(TABLE_COLUMN_MAX) as u16)
+        })
+        .collect();
+
+    let header = Row::new(rows[0].iter().map(String::as_str)).style(
+        Style::default()
+            .fg(Color::Cyan)
+            .add_modifier(Modifier::BOLD),
+    );
+    let body = rows[1..]
+        .iter()
+        .skip(scroll)
+        .map(|row| Row::new(row.iter().map(String::as_str)));
+    Table::new(body, widths).header(header)
+}
+
+/// Green for success, yellow for redirects, red for errors.
+fn status_style(status: u16) -> Style {
+    match status {
+        200..=299 => Style::default().fg(Color::Green),
+        300..=399 => Style::default().fg(Color::Yellow),
+        _ => Style::default().fg(Color::Red),
+    }
+}
+
+/// The prompt while one is open, otherwise the last status message or a
+/// short reminder of the global keys.
+fn status_bar(app: &App) -> Paragraph<'static> {
+    let line = if let Some(prompt) = &app.prompt {
+        Line::from(vec![
+            Span::styled(
+                format!("{}: ", prompt.label),
+                Style::default().fg(Color::Yellow),
+            ),
+            Span::raw(format!("{}_", prompt.input)),
+            Span::styled(
+                "  (Enter: confirm, Esc: cancel)",
+                Style::default().fg(Color::DarkGray),
+            ),
+        ])
+    } else if let Some(status) = &app.status {
+        Line::from(status.clone())
+    } else {
+        Line::from(Span::styled(
+            format!(
+                "{} | {}: clear response | {}",
+                focus_help(app),
+                app.config.clear_response_key,
+                GLOBAL_HELP
+            ),
+            Style::default().fg(Color::DarkGray),
+        ))
+    };
+    Paragraph::new(line)
+}
+
+/// Requests sent so far, then method, status and duration of the last one,
+/// kept while other messages come and go.
+fn last_sent_badge(app: &App) -> Option<Line<'static>> {
+    let last = app.last_sent.as_ref()?;
+    let (status, style) = match last.status {
+        Some(status) => (status.to_string(), status_style(status)),
+        None => ("failed".to_string(), Style::default().fg(Color::Red)),
+    };
+    let mut spans = vec![
+        Span::raw(format!(" #{} {} ", app.stats.sent, last.method)),
+        Span::styled(status, style.add_modifier(Modifier::BOLD)),
+    ];
+    if let Some(elapsed) = last.elapsed {
+        spans.push(Span::raw(format!(" {} ms", elapsed.as_millis())));
+    }
+    spans.push(Span::raw(" "));
+    Some(Line::from(spans).style(Style::default().add_modifier(Modifier::REVERSED)))
+}
+
+/// Keys available everywhere, shown after the ones of the focused area.
+const GLOBAL_HELP: &str = "Tab: focus | Ctrl+Enter: send | Alt+Enter: send & save | Alt+V: verbose send | Alt+E: load .env | Ctrl+S: save | Ctrl+B: batch | Ctrl+K/Alt+K: cookie override/jar | Ctrl+U: Host/SNI | Ctrl+F: JSON/form | Alt+B: base64 body | Ctrl+L/W: format/check body | Ctrl+X Ctrl+E/R: $EDITOR body/expected response | F2: history | F3: pin baseline | F4: A/B compare | Ctrl+E/O: export/import .http | Ctrl+R/P: record/replay | Ctrl+G: timestamps | F5: reload config | F6: settings | F7: GraphQL schema | F8: poll | F9: session stats | Esc: quit";
+
+/// What Enter and the other keys do in the focused area.
+fn focus_help(app: &App) -> &'static str {
+    match app.focus {
+        Focus::Url => "Enter: send | Up/Down: method | Ctrl+D: decode query | Ctrl+T: http/https",
+        Focus::Headers => "Enter: new header | Up/Down: row | Ctrl+Y: duplicate row",
+        Focus::Params => {
+            "Enter: new param | Up/Down: row | Ctrl+Y: duplicate row | edits update the URL"
+        }
+        Focus::Body if app.config.body_enter_sends => {
+            "Enter: send | Ctrl+N: snippets | Tab: next snippet field"
+        }
+        Focus::Body => "Enter: newline | Ctrl+N: snippets | Tab: next snippet field",
+        Focus::Response => {
+            "Up/Down/PgUp/PgDn: scroll | e: jump to error | c/o/1-9: collapse/expand/depth | s: sort keys | /: search | n/N: next/prev match | r/i: regex/case | b: base64 decode | v: expected/baseline diff | p: $PAGER | m: save metadata | Enter: send"
+        }
+    }
+}
@@ -0,0 +1,113 @@
+//! Checking and re-indenting XML bodies.
+
+use quick_xml::{events::Event, Reader, Writer};
+
+/// Whether a `Content-Type` value names an XML type.
+pub fn is_xml(content_type: &str) -> bool {
+    let mime = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    mime.ends_with("/xml") || mime.ends_with("+xml")
+}
+
+/// `text` indented by two spaces per level, or a description of the first
+/// well-formedness error with its line and column.
+pub fn pretty(text: &str) -> Result<String, String> {
+    let mut reader = Reader::from_str(text);
+    reader.config_mut().trim_text(true);
+    let mut writer = Writer::new_with_indent(Vec::new(), b' ', 2);
+    // Open elements, since the reader doesn't complain about them at the end
+    let mut open = Vec::new();
+    let mut roots = 0;
+
+    loop {
+        let event = reader
+            .read_event()
+            .map_err(|e| at(text, reader.error_position() as usize, &e.to_string()))?;
+        match &event {
+            Event::Eof => break,
+            Event::Start(start) => {
+                if open.is_empty() {
+                    roots += 1;
+                }
+                open.push(start.name().into_inner().to_string());
+            }
+            Event::Empty(_) if open.is_empty() => roots += 1,
+            Event::End(_) => {
+                open.pop();
+            }
+            Event::Text(_) | Event::CData(_) if open.is_empty() => {
+                let position = reader.buffer_position() as usize;
+                return Err(at(text, position, "text outside the root element"));
+            }
+            _ => {}
+        }
+        if roots > 1 {
+            let position = reader.buffer_position() as usize;
+            return Err(at(text, position, "more than one root element"));
+        }
+        writer.write_event(event).map_err(|e| e.to_string())?;
+    }
+
+    if let Some(name) = open.last() {
+        return Err(at(text, text.len(), &format!("<{}> is never closed", name)));
+    }
+    if roots == 0 {
+        return Err("no root element".into());
+    }
+    String::from_utf8(writer.into_inner()).map_err(|e| e.to_string())
+}
+
+/// `message` prefixed with the line and column of byte `offset` in `text`.
+fn at(text: &str, offset: usize, message: &str) -> String {
+    let before = &text[..text.floor_char_boundary(offset.min(text.len()))];
+    let line = before.matches('\n').count() + 1;
+    let column = before
+        .rsplit('\n')
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .count()
+        + 1;
+    format!("line {}, column {}: {}", line, column, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xml_types_are_recognized_by_their_suffix() {
+        assert!(is_xml("application/xml"));
+        assert!(is_xml("Application/ATOM+XML; charset=utf-8"));
+        assert!(is_xml("text/xml"));
+        assert!(!is_xml("application/json"));
+        assert!(!is_xml("application/xml-dtd"));
+    }
+
+    #[test]
+    fn documents_are_reindented() {
+        assert_eq!(
+            pretty("<a><b x=\"1\">text</b>  <c/></a>").unwrap(),
+            "<a>\n  <b x=\"1\">text</b>\n  <c/>\n</a>"
+        );
+    }
+
+    #[test]
+    fn errors_name_their_position() {
+        let mismatched = pretty("<a>\n  <b></a>").unwrap_err();
+        assert!(mismatched.starts_with("line 2, column "), "{}", mismatched);
+        assert_eq!(
+            pretty("<a></a>\n<b/>"),
+            Err("line 2, column 5: more than one root element".into())
+        );
+        assert_eq!(
+            pretty("<a>"),
+            Err("line 1, column 4: <a> is never closed".into())
+        );
+        assert_eq!(pretty("<!-- only -->"), Err("no root element".into()));
+    }
+}